@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::relative_file_name;
+use crate::metrics::Metric;
+use crate::outcome::{Category, FileResult, Outcome};
+use crate::setting::Settings;
+
+// Known failure of single tool or pair of tools on single file
+#[derive(Serialize, Deserialize)]
+pub struct BaselineEntry {
+    // Path relative to folder with files to check
+    pub file: String,
+    // Name of tool or pair of tools e.g. `thorvg vs rsvg`
+    pub subject: String,
+    pub outcome: String,
+    // First of `broken_metrics`, scores are compared only when the same metric was used
+    pub metric: Option<Metric>,
+    pub score: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Baseline {
+    pub entries: Vec<BaselineEntry>,
+}
+
+#[derive(Default)]
+pub struct BaselineChanges {
+    pub new_failures: Vec<String>,
+    pub fixed: Vec<String>,
+    pub changed_scores: Vec<String>,
+}
+
+impl Baseline {
+    // Missing file is treated as empty baseline, so first run can be used to create it
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            eprintln!("Baseline file {path} not exists, all failures will be treated as new");
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read baseline file {path} - {e}"))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse baseline file {path} - {e}"))
+    }
+
+    pub fn save(&self, path: &str) {
+        let content = serde_json::to_string_pretty(self).expect("Baseline should be always serializable");
+        match fs::write(path, content) {
+            Ok(()) => println!("Saved baseline with {} known failures to {path}", self.entries.len()),
+            Err(e) => eprintln!("Failed to save baseline to {path} - {e}"),
+        }
+    }
+
+    // Entries of files checked in this run are replaced with their current failures, entries of other files are kept
+    // so updating baseline after run on part of files (e.g. shard or sample) not removes known failures of other files
+    pub fn updated(mut self, settings: &Settings, results: &[FileResult]) -> Self {
+        let checked_files = results.iter().map(|e| relative_file_name(settings, &e.file)).collect::<BTreeSet<_>>();
+        self.entries.retain(|e| !checked_files.contains(&e.file));
+        for file_result in results {
+            for (subject, (outcome, score)) in file_subjects(settings, file_result) {
+                if outcome.is_success() {
+                    continue;
+                }
+                self.entries.push(BaselineEntry {
+                    file: relative_file_name(settings, &file_result.file),
+                    subject,
+                    outcome: outcome.kind().to_string(),
+                    metric: score.map(|_| settings.broken_metrics[0]),
+                    score,
+                });
+            }
+        }
+        self.entries.sort_by(|a, b| (&a.file, &a.subject).cmp(&(&b.file, &b.subject)));
+        self
+    }
+
+    // Only files checked in this run are compared, so running on part of files not marks other files as fixed
+    pub fn compare(&self, settings: &Settings, results: &[FileResult]) -> BaselineChanges {
+        let known_failures = self
+            .entries
+            .iter()
+            .map(|e| ((e.file.as_str(), e.subject.as_str()), e))
+            .collect::<BTreeMap<_, _>>();
+        let metric = settings.broken_metrics[0];
+
+        let mut changes = BaselineChanges::default();
+        for file_result in results {
+            let file = relative_file_name(settings, &file_result.file);
+            for (subject, (outcome, score)) in file_subjects(settings, file_result) {
+                let name = format!("{file} - {subject}");
+                match (known_failures.get(&(file.as_str(), subject.as_str())), outcome.is_success()) {
+                    (None, true) => {}
+                    (None, false) => changes.new_failures.push(format!("{name} - {outcome}")),
+                    (Some(entry), true) => changes.fixed.push(format!("{name} - was {}", entry.outcome)),
+                    (Some(entry), false) if entry.outcome != outcome.kind() => {
+                        changes.new_failures.push(format!("{name} - {outcome}, was {}", entry.outcome));
+                    }
+                    (Some(entry), false) => {
+                        if let (Some(old_score), Some(new_score), Some(old_metric)) = (entry.score, score, entry.metric) {
+                            if old_metric == metric && (new_score - old_score).abs() > settings.baseline_score_tolerance {
+                                changes
+                                    .changed_scores
+                                    .push(format!("{name} - {metric} changed from {old_score} to {new_score}"));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        changes
+    }
+}
+
+impl BaselineChanges {
+    pub fn print(&self) {
+        for (title, items) in [
+            ("New failures", &self.new_failures),
+            ("Fixed files", &self.fixed),
+            ("Changed scores", &self.changed_scores),
+        ] {
+            if items.is_empty() {
+                continue;
+            }
+            println!("{title} ({}):", items.len());
+            for item in items {
+                println!("  {item}");
+            }
+        }
+        println!(
+            "Compared with baseline - {} new failures, {} fixed files, {} changed scores",
+            self.new_failures.len(),
+            self.fixed.len(),
+            self.changed_scores.len()
+        );
+        if !self.fixed.is_empty() || !self.changed_scores.is_empty() {
+            println!("Baseline can be regenerated with `--update-baseline`");
+        }
+    }
+}
+
+// Outcome of every tool and every pair of tools, with value of first of `broken_metrics`
+fn file_subjects<'a>(settings: &Settings, file_result: &'a FileResult) -> Vec<(String, (&'a Outcome, Option<f64>))> {
+    if file_result.ignore_reason.is_some() {
+        return Vec::new();
+    }
+    let metric = settings.broken_metrics[0];
+    // Tools skipped by ignore rule are not failures
+    let tools = file_result
+        .tools
+        .iter()
+        .filter(|e| e.outcome.category() != Category::Ignored)
+        .map(|e| (e.tool.clone(), (&e.outcome, None)));
+    let pairs = file_result.pairs.iter().map(|e| {
+        let score = e.metrics.as_ref().map(|metrics| metrics.value(metric)).filter(|e| e.is_finite());
+        (format!("{} vs {}", e.first_tool, e.other_tool), (&e.outcome, score))
+    });
+    tools.chain(pairs).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::ImageMetrics;
+    use crate::outcome::{PairResult, ToolResult};
+
+    fn tool_result(tool: &str, outcome: Outcome) -> ToolResult {
+        ToolResult {
+            tool: tool.to_string(),
+            outcome,
+            exit_code: None,
+            duration: None,
+            output: String::new(),
+        }
+    }
+
+    // Pair of tools `a vs b`, with difference between them reported as `hash_difference`
+    fn file_result(file: &str, tool_outcome: Outcome, hash_difference: Option<u32>) -> FileResult {
+        let mut file_result = FileResult::new(file, None);
+        file_result.tools.push(tool_result("a", tool_outcome));
+        file_result.tools.push(tool_result("b", Outcome::Success));
+        file_result.pairs.push(PairResult {
+            first_tool: "a".to_string(),
+            other_tool: "b".to_string(),
+            outcome: match hash_difference {
+                Some(_) => Outcome::VisualDifference {
+                    exceeded: vec![Metric::Hash],
+                },
+                None => Outcome::Success,
+            },
+            metrics: hash_difference.map(|hash_difference| ImageMetrics {
+                hash_difference,
+                differing_pixels: 0,
+                max_channel_delta: 0,
+                rmse: 0.0,
+                psnr: f64::INFINITY,
+                ssim: 1.0,
+            }),
+        });
+        file_result
+    }
+
+    fn baseline(results: &[FileResult]) -> Baseline {
+        Baseline::default().updated(&Settings::default(), results)
+    }
+
+    #[test]
+    fn keeps_only_failures() {
+        let baseline = baseline(&[
+            file_result("ok.svg", Outcome::Success, None),
+            file_result("broken.svg", Outcome::Timeout, Some(20)),
+        ]);
+        let entries = baseline
+            .entries
+            .iter()
+            .map(|e| (e.file.as_str(), e.subject.as_str(), e.outcome.as_str(), e.score))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("broken.svg", "a", "timeout", None),
+                ("broken.svg", "a vs b", "visual_difference", Some(20.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_no_changes_for_the_same_results() {
+        let results = [
+            file_result("ok.svg", Outcome::Success, None),
+            file_result("broken.svg", Outcome::Timeout, Some(20)),
+        ];
+        let changes = baseline(&results).compare(&Settings::default(), &results);
+        assert!(changes.new_failures.is_empty());
+        assert!(changes.fixed.is_empty());
+        assert!(changes.changed_scores.is_empty());
+    }
+
+    #[test]
+    fn reports_new_failures() {
+        let baseline = baseline(&[file_result("file.svg", Outcome::Success, None)]);
+        let changes = baseline.compare(&Settings::default(), &[file_result("file.svg", Outcome::NonZeroExit { code: 1 }, None)]);
+        assert_eq!(changes.new_failures, ["file.svg - a - exited with code 1"]);
+    }
+
+    #[test]
+    fn reports_fixed_failures() {
+        let baseline = baseline(&[file_result("file.svg", Outcome::Timeout, None)]);
+        let changes = baseline.compare(&Settings::default(), &[file_result("file.svg", Outcome::Success, None)]);
+        assert_eq!(changes.fixed, ["file.svg - a - was timeout"]);
+        assert!(changes.new_failures.is_empty());
+    }
+
+    #[test]
+    fn reports_changed_outcome_as_new_failure() {
+        let baseline = baseline(&[file_result("file.svg", Outcome::Timeout, None)]);
+        let changes = baseline.compare(&Settings::default(), &[file_result("file.svg", Outcome::Signal { signal: 11 }, None)]);
+        assert_eq!(changes.new_failures.len(), 1);
+        assert!(changes.new_failures[0].ends_with(", was timeout"));
+        assert!(changes.fixed.is_empty());
+    }
+
+    #[test]
+    fn reports_only_scores_changed_more_than_tolerance() {
+        let settings = Settings::default();
+        let baseline = baseline(&[file_result("file.svg", Outcome::Success, Some(20))]);
+
+        let within_tolerance = baseline.compare(&settings, &[file_result("file.svg", Outcome::Success, Some(24))]);
+        assert!(within_tolerance.changed_scores.is_empty());
+
+        let changes = baseline.compare(&settings, &[file_result("file.svg", Outcome::Success, Some(30))]);
+        assert_eq!(changes.changed_scores, ["file.svg - a vs b - hash changed from 20 to 30"]);
+        assert!(changes.new_failures.is_empty());
+    }
+
+    #[test]
+    fn not_compares_files_not_checked_in_run() {
+        let baseline = baseline(&[
+            file_result("checked.svg", Outcome::Timeout, None),
+            file_result("other.svg", Outcome::Timeout, None),
+        ]);
+        let changes = baseline.compare(&Settings::default(), &[file_result("checked.svg", Outcome::Timeout, None)]);
+        assert!(changes.fixed.is_empty());
+    }
+
+    #[test]
+    fn ignores_tools_skipped_by_ignore_rule() {
+        let ignored = Outcome::Ignored {
+            rule: "rule".to_string(),
+            reason: "reason".to_string(),
+        };
+        let baseline = baseline(&[file_result("file.svg", ignored, None)]);
+        assert!(baseline.entries.is_empty());
+    }
+
+    #[test]
+    fn update_keeps_entries_of_not_checked_files() {
+        let settings = Settings::default();
+        let baseline = baseline(&[
+            file_result("checked.svg", Outcome::Timeout, None),
+            file_result("other.svg", Outcome::Timeout, None),
+        ])
+        .updated(&settings, &[file_result("checked.svg", Outcome::Success, None)]);
+        let files = baseline.entries.iter().map(|e| e.file.as_str()).collect::<Vec<_>>();
+        assert_eq!(files, ["other.svg"]);
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::process::{self, Command};
+
+use rayon::prelude::*;
+
+use crate::compare::{check_file_quietly, collect_files_to_check};
+use crate::outcome::{Category, Outcome};
+use crate::render_cache::RenderCache;
+use crate::scratch::RenderJob;
+use crate::setting::Settings;
+
+const FIRST_BAD_COMMIT_MARKER: &str = "is the first bad commit";
+const ONLY_SKIPPED_MARKER: &str = "There are only 'skip'ped commits left to test";
+
+pub struct BisectOptions {
+    pub repository: String,
+    // Run with `sh -c` inside repository, after each checkout
+    pub build_command: String,
+    pub good: String,
+    pub bad: String,
+}
+
+// Files which started to fail in the same range of commits
+struct Group {
+    files: Vec<String>,
+    good: String,
+    bad: String,
+}
+
+enum GroupResult {
+    FirstBadCommit(String),
+    // Build failed on some commits, so git was not able to find exact commit
+    Undetermined(String),
+}
+
+enum GroupStep {
+    Finished(Group, GroupResult),
+    // Files start to fail at different commits, so each of new groups is bisected separately
+    Split(Vec<Group>),
+}
+
+// Ends bisect session and checks out branch or commit checked out before bisecting, also when bisect fails
+// Must be dropped before exiting with `process::exit`, because it not runs destructors
+struct RestoreRepository<'a> {
+    repository: &'a str,
+    original_head: String,
+}
+
+impl Drop for RestoreRepository<'_> {
+    fn drop(&mut self) {
+        let _ = git(self.repository, &["bisect", "reset"]);
+        if let Err(e) = git(self.repository, &["checkout", "--quiet", &self.original_head]) {
+            eprintln!("Failed to restore {} in repository - {e}", self.original_head);
+        }
+    }
+}
+
+struct Bisect<'a> {
+    settings: &'a Settings,
+    options: &'a BisectOptions,
+    // Commit which is currently built, `None` when build failed
+    built_commit: Option<String>,
+    // Commits which failed to build, or at which tools could not be run, they are skipped
+    failed_builds: Vec<String>,
+    // Results of files are cached, so files moved to other group not need to be checked again
+    file_results: HashMap<(String, String), bool>,
+}
+
+// Files to check should be files, that fails at bad commit, they are rendered with tools from settings,
+// so at least one of them should use binary built inside repository
+pub fn bisect_files(settings: &Settings, options: &BisectOptions) {
+    let files_to_check = collect_files_to_check(settings);
+    let original_head = git(&options.repository, &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap_or_else(|e| exit_with_error(&e));
+    let original_head = match original_head.trim() {
+        // Detached head
+        "HEAD" => git(&options.repository, &["rev-parse", "HEAD"]).unwrap_or_else(|e| exit_with_error(&e)),
+        branch => branch.to_string(),
+    };
+    let good = resolve_commit(&options.repository, &options.good);
+    let bad = resolve_commit(&options.repository, &options.bad);
+
+    let restore_repository = RestoreRepository {
+        repository: &options.repository,
+        original_head,
+    };
+    let mut bisect = Bisect {
+        settings,
+        options,
+        built_commit: None,
+        failed_builds: Vec::new(),
+        file_results: HashMap::new(),
+    };
+    let results = bisect.run(&files_to_check, good, bad);
+    drop(restore_repository);
+    let results = results.unwrap_or_else(|e| exit_with_error(&e));
+
+    for (group, result) in &results {
+        match result {
+            GroupResult::FirstBadCommit(commit) => {
+                let description = git(&options.repository, &["log", "-1", "--format=%h %s", commit]).unwrap_or_else(|_| commit.clone());
+                println!("First bad commit {} - {} files:", description.trim(), group.files.len());
+            }
+            GroupResult::Undetermined(message) => println!("Not found first bad commit - {} files:\n{message}", group.files.len()),
+        }
+        for file in &group.files {
+            println!("  {file}");
+        }
+    }
+    if !bisect.failed_builds.is_empty() {
+        println!("Build failed or tools could not be run at commits: {}", bisect.failed_builds.join(", "));
+    }
+}
+
+impl Bisect<'_> {
+    // Errors are returned instead of exiting, so repository can be restored before exit
+    fn run(&mut self, files_to_check: &[String], good: String, bad: String) -> Result<Vec<(Group, GroupResult)>, String> {
+        let Some(failing_at_bad) = self.failing_files(&bad, files_to_check)? else {
+            return Err(format!("Failed to build bad commit {bad}"));
+        };
+        let Some(failing_at_good) = self.failing_files(&good, &failing_at_bad)? else {
+            return Err(format!("Failed to build good commit {good}"));
+        };
+        let not_failing = files_to_check.iter().filter(|e| !failing_at_bad.contains(e)).collect::<Vec<_>>();
+        if !not_failing.is_empty() {
+            println!("{} files not fail at bad commit, so are not bisected", not_failing.len());
+        }
+        if !failing_at_good.is_empty() {
+            println!("{} files fail also at good commit, so are not bisected", failing_at_good.len());
+        }
+        let files = failing_at_bad.into_iter().filter(|e| !failing_at_good.contains(e)).collect::<Vec<_>>();
+
+        let mut groups = vec![Group { files, good, bad }];
+        let mut results = Vec::new();
+        while let Some(group) = groups.pop() {
+            if group.files.is_empty() {
+                continue;
+            }
+            match self.bisect_group(group)? {
+                GroupStep::Finished(group, result) => results.push((group, result)),
+                GroupStep::Split(new_groups) => groups.extend(new_groups),
+            }
+        }
+        Ok(results)
+    }
+
+    fn bisect_group(&mut self, group: Group) -> Result<GroupStep, String> {
+        let repository = &self.options.repository;
+        let _ = git(repository, &["bisect", "reset"]);
+        let mut output = git(repository, &["bisect", "start", &group.bad, &group.good])?;
+        loop {
+            if let Some(line) = output.lines().find(|e| e.contains(FIRST_BAD_COMMIT_MARKER)) {
+                let commit = line.split_whitespace().next().unwrap_or_default().to_string();
+                return Ok(GroupStep::Finished(group, GroupResult::FirstBadCommit(commit)));
+            }
+            if output.contains(ONLY_SKIPPED_MARKER) {
+                return Ok(GroupStep::Finished(group, GroupResult::Undetermined(output)));
+            }
+
+            let current = git(repository, &["rev-parse", "HEAD"])?;
+            let current = current.trim().to_string();
+            let mark = match self.failing_files(&current, &group.files)? {
+                None => "skip",
+                Some(failing) if failing.is_empty() => "good",
+                Some(failing) if failing.len() == group.files.len() => "bad",
+                Some(failing) => {
+                    println!(
+                        "Files starts to fail at different commits, splitting group of {} files",
+                        group.files.len()
+                    );
+                    let (failing, passing) = group.files.into_iter().partition(|e| failing.contains(e));
+                    return Ok(GroupStep::Split(vec![
+                        Group {
+                            files: failing,
+                            good: group.good,
+                            bad: current.clone(),
+                        },
+                        Group {
+                            files: passing,
+                            good: current,
+                            bad: group.bad,
+                        },
+                    ]));
+                }
+            };
+            println!("Commit {current} is {mark}");
+            output = match git(repository, &["bisect", mark]) {
+                Ok(t) => t,
+                // Git returns error, when only skipped commits left
+                Err(e) if e.contains(ONLY_SKIPPED_MARKER) => e,
+                Err(e) => return Err(e),
+            };
+        }
+    }
+
+    // Returns `None` when commit cannot be built, or tool cannot be run at it e.g. because binary was not created
+    // Files are checked without saving anything to output folders, because results at intermediate commits are not interesting
+    fn failing_files(&mut self, commit: &str, files: &[String]) -> Result<Option<Vec<String>>, String> {
+        let not_checked = files
+            .iter()
+            .filter(|e| !self.file_results.contains_key(&(commit.to_string(), (*e).clone())))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !not_checked.is_empty() {
+            if !self.build(commit)? {
+                return Ok(None);
+            }
+            let results = not_checked
+                .par_iter()
+                .map(|file| {
+                    let (file_result, _) = check_file_quietly(self.settings, &RenderCache::disabled(), &RenderJob::create(), file);
+                    if let Some(tool_result) = file_result.tools.iter().find(|e| matches!(e.outcome, Outcome::FailedToRun { .. })) {
+                        return Err(format!("{} - {}", tool_result.tool, tool_result.outcome));
+                    }
+                    let category = file_result.category();
+                    Ok((file.clone(), matches!(category, Category::Broken | Category::Problematic)))
+                })
+                .collect::<Result<Vec<_>, String>>();
+            let results = match results {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("Failed to run tool at commit {commit}, so it is skipped - {e}");
+                    self.built_commit = None;
+                    self.failed_builds.push(commit.to_string());
+                    return Ok(None);
+                }
+            };
+            for (file, failed) in results {
+                self.file_results.insert((commit.to_string(), file), failed);
+            }
+        }
+        Ok(Some(
+            files
+                .iter()
+                .filter(|e| self.file_results[&(commit.to_string(), (*e).clone())])
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    // Returns `false` when build failed
+    fn build(&mut self, commit: &str) -> Result<bool, String> {
+        if self.built_commit.as_deref() == Some(commit) {
+            return Ok(true);
+        }
+        if self.failed_builds.iter().any(|e| e == commit) {
+            return Ok(false);
+        }
+        self.built_commit = None;
+        git(&self.options.repository, &["checkout", "--quiet", commit])?;
+        println!("Building commit {commit}");
+        let output = Command::new("sh")
+            .args(["-c", &self.options.build_command])
+            .current_dir(&self.options.repository)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                self.built_commit = Some(commit.to_string());
+                Ok(true)
+            }
+            Ok(output) => {
+                println!(
+                    "Failed to build commit {commit}, {}\n{}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                self.failed_builds.push(commit.to_string());
+                Ok(false)
+            }
+            Err(e) => Err(format!("Failed to run build command `{}` - {e}", self.options.build_command)),
+        }
+    }
+}
+
+fn resolve_commit(repository: &str, commit: &str) -> String {
+    match git(repository, &["rev-parse", "--verify", &format!("{commit}^{{commit}}")]) {
+        Ok(t) => t.trim().to_string(),
+        Err(e) => exit_with_error(&e),
+    }
+}
+
+// Returns stdout and stderr of git command
+fn git(repository: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repository)
+        .output()
+        .map_err(|e| format!("Failed to run git in {repository} - {e}"))?;
+    let all_output = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    if output.status.success() {
+        Ok(all_output)
+    } else {
+        Err(format!("Command `git {}` failed in {repository}\n{all_output}", args.join(" ")))
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
@@ -0,0 +1,104 @@
+use clap::{Parser, Subcommand};
+
+use crate::setting::SETTINGS_FILE;
+
+#[derive(Parser)]
+#[command(version, about = "Finds differences and crashes in svg renderers")]
+pub struct Cli {
+    /// Path to settings file
+    #[arg(short, long, default_value = SETTINGS_FILE, global = true)]
+    pub config: String,
+
+    /// Overrides value from settings file e.g. `--set general.max_difference=5` or `--set tool[0].path=rsvg-convert`
+    #[arg(long = "set", value_name = "SECTION.KEY=VALUE", global = true)]
+    pub overrides: Vec<String>,
+
+    /// Saves failures found in this run as new baseline, in `baseline_file` (only in compare and golden modes)
+    #[arg(long, global = true)]
+    pub update_baseline: bool,
+
+    /// Reuses results saved in `resume_folder` by previous run, so only files not checked yet or changed are checked
+    #[arg(long, global = true)]
+    pub resume: bool,
+
+    /// Checks only N randomly selected files, same as `--set general.sample_files=N`
+    #[arg(long, value_name = "N", global = true)]
+    pub sample: Option<usize>,
+
+    /// Seed used to select files with `--sample`, same as `--set general.sample_seed=S`
+    #[arg(long, value_name = "S", global = true)]
+    pub seed: Option<u64>,
+
+    /// Checks only part of sorted files e.g. `--shard 2/4`, same as `--set general.shard=2/4`
+    #[arg(long, value_name = "INDEX/COUNT", global = true)]
+    pub shard: Option<String>,
+
+    #[command(subcommand)]
+    pub mode: Option<Mode>,
+}
+
+impl Cli {
+    // Shortcut arguments are applied after `--set` overrides, so they always win
+    pub fn all_overrides(&self) -> Vec<String> {
+        let mut overrides = self.overrides.clone();
+        if let Some(sample) = self.sample {
+            overrides.push(format!("general.sample_files={sample}"));
+        }
+        if let Some(seed) = self.seed {
+            overrides.push(format!("general.sample_seed={seed}"));
+        }
+        if let Some(shard) = &self.shard {
+            overrides.push(format!("general.shard={shard}"));
+        }
+        overrides
+    }
+}
+
+#[derive(Subcommand, Default)]
+pub enum Mode {
+    /// Renders svg files with all tools and compares results (default)
+    #[default]
+    Compare,
+    /// Finds svg files that crash, leak or time out in thorvg
+    Thorvg,
+    /// Finds lottie files that crash, leak or time out in thorvg
+    Lottie,
+    /// Renders svg files with single tool and compares results with reference png files from `golden_folder`
+    Golden {
+        /// Saves current renders as new reference png files, instead of comparing with them
+        #[arg(long)]
+        update_goldens: bool,
+    },
+    /// Finds first commit of tool repository, at which files from `folder_with_files_to_check` started to fail, using `git bisect`
+    Bisect {
+        /// Path to git checkout of tool, binary built there should be used by one of [[tool]] sections
+        #[arg(long)]
+        repository: String,
+        /// Command run with `sh -c` inside repository, after checking out each commit
+        #[arg(long)]
+        build_command: String,
+        /// Commit at which files works fine
+        #[arg(long)]
+        good: String,
+        /// Commit at which files fail
+        #[arg(long)]
+        bad: String,
+    },
+    /// Removes elements, attributes and path commands from svg file, as long as it fails in the same way, and saves the smallest found reproducer
+    Reduce {
+        /// Svg file to reduce
+        file: String,
+        /// Checks file only with thorvg like in thorvg mode, instead of rendering it with all tools
+        #[arg(long)]
+        thorvg: bool,
+        /// Path to reduced file, by default `<name>_reduced.svg` in output folder
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Removes renders of tool builds not used by any [[tool]] section from `render_cache_folder`
+    PruneCache {
+        /// Removes also renders of current tool builds, which were not used for N days
+        #[arg(long, value_name = "N")]
+        max_age_days: Option<u64>,
+    },
+}
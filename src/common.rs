@@ -1,16 +1,85 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-pub fn save_problematic_file(problematic_files_path: &str, svg_tool_name: &str, broken_svg_path: &str, remove_problematic_files_after_copying: bool) {
-    let file_name = Path::new(broken_svg_path).file_name().unwrap().to_str().unwrap().to_string();
-    let new_path = format!("{problematic_files_path}/{svg_tool_name}");
-    let new_file_path = format!("{new_path}/{file_name}");
+use crate::image_comparison::copy_to_file_name;
+use crate::setting::Settings;
 
+// Copies file to folder of tool which failed, returns folder where it was copied
+pub fn save_problematic_file(settings: &Settings, svg_tool_name: &str, broken_svg_path: &str) -> String {
+    let new_path = mirrored_output_folder(settings, &format!("{}/{svg_tool_name}", settings.problematic_files_path), broken_svg_path);
     let _ = fs::create_dir_all(&new_path);
-    if remove_problematic_files_after_copying {
-        let _ = fs::copy(broken_svg_path, new_file_path);
-        let _ = fs::remove_file(broken_svg_path);
+    copy_to_file_name(broken_svg_path, &new_path);
+    new_path
+}
+
+// Path relative to folder with files to check, used to identify file between runs
+// Files from archives use names of archive members
+fn relative_path(settings: &Settings, file: &str) -> Option<PathBuf> {
+    settings.input_folders.iter().find_map(|input| {
+        let relative_path = Path::new(file).strip_prefix(&input.folder).ok()?;
+        match &input.name {
+            Some(name) => Some(Path::new(name).join(relative_path)),
+            None => Some(relative_path.to_path_buf()),
+        }
+    })
+}
+
+// Location of file extracted from archive or .svgz file e.g. `SVG5BigPack.zip/a/b.svg`, so it can be found after extracted files are removed
+pub fn original_file_name(settings: &Settings, file: &str) -> Option<String> {
+    settings.input_folders.iter().find_map(|input| {
+        let source = input.source.as_ref()?;
+        let relative_path = Path::new(file).strip_prefix(&input.folder).ok()?;
+        // `.svg` added to name of decompressed file is removed
+        let relative_path = if input.svgz {
+            relative_path.with_extension("")
+        } else {
+            relative_path.to_path_buf()
+        };
+        Some(format!("{source}/{}", relative_path.to_string_lossy()))
+    })
+}
+
+pub fn relative_file_name(settings: &Settings, file: &str) -> String {
+    match relative_path(settings, file) {
+        Some(t) => t.to_string_lossy().to_string(),
+        None => file.to_string(),
+    }
+}
+
+// Output folder with the same subfolders as in checked folder, so files with same names in different subfolders don't overwrite each other
+// Folder is not created
+pub fn mirrored_output_folder(settings: &Settings, output_folder: &str, file: &str) -> String {
+    match relative_path(settings, file).as_deref().and_then(Path::parent) {
+        Some(parent) if !parent.as_os_str().is_empty() => format!("{output_folder}/{}", parent.to_string_lossy()),
+        _ => output_folder.to_string(),
+    }
+}
+
+// Png saved with name of svg file, only extension is replaced, so `.svg` inside folder names is kept
+pub fn png_file_name(svg_file: &str, png_name_ending: &str) -> String {
+    let file_stem = Path::new(svg_file).file_stem().unwrap_or_default().to_string_lossy();
+    format!("{file_stem}{png_name_ending}")
+}
+
+// Png placed in the same folder as svg file
+pub fn png_beside_svg(svg_file: &str, png_name_ending: &str) -> String {
+    Path::new(svg_file)
+        .with_file_name(png_file_name(svg_file, png_name_ending))
+        .to_string_lossy()
+        .to_string()
+}
+
+// Relative path would point to other file, when tool is run in other working directory
+pub fn absolute_path(path: &str) -> String {
+    std::path::absolute(path).map_or_else(|_| path.to_string(), |e| e.to_string_lossy().to_string())
+}
+
+// Path with separator is made absolute, but symlinks are kept, because some tools check name under which they were run
+// Tools from PATH are searched the same way as when checking that tool exists
+pub fn resolve_program(path: &str) -> String {
+    if path.contains('/') {
+        absolute_path(path)
     } else {
-        fs::copy(broken_svg_path, new_file_path).unwrap();
+        which::which(path).map_or_else(|_| path.to_string(), |e| e.to_string_lossy().to_string())
     }
 }
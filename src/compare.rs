@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+use image::DynamicImage;
+use rayon::prelude::*;
+
+use crate::baseline::Baseline;
+use crate::common::{mirrored_output_folder, original_file_name, save_problematic_file};
+use crate::diff_image::save_diff_images;
+use crate::feature_statistics::FeatureStatistics;
+use crate::features::SvgFeatures;
+use crate::find_files;
+use crate::html_report::{save_html_report, save_report_images, ReportImages};
+use crate::ignore_rules::{find_input_ignore_rule, find_output_ignore_rule};
+use crate::image_comparison::{compare_images, copy_to_file_name, load_image, pair_output_folder};
+use crate::interrupt::{is_interrupted, print_interrupted, stop_checking_on_interrupt};
+use crate::json_report::save_json_results;
+use crate::junit_report::save_junit_report_for_pairs;
+use crate::outcome::{Category, FileResult, Outcome, PairResult, Summary, ToolResult};
+use crate::render_cache::RenderCache;
+use crate::results_log::ResultsLog;
+use crate::scratch::RenderJob;
+use crate::setting::{Settings, ToolSettings};
+use crate::svg_2_png::{output_png, render_with_cache};
+
+pub fn compare_svg_files(settings: &Settings, update_baseline: bool, resume: bool) {
+    let baseline = load_baseline(settings);
+    let files_to_check = collect_files_to_check(settings);
+    prepare_output_folders(settings, resume);
+
+    let tools = settings.tools.iter().collect::<Vec<_>>();
+    let render_cache = RenderCache::open(settings, &tools);
+    let results_log = ResultsLog::open(settings, "compare", render_cache.fingerprints(), resume);
+    let (results, report_images) = check_files(settings, &files_to_check, &results_log, |source_file, job| {
+        check_file(settings, &render_cache, job, source_file)
+    });
+    render_cache.print_statistics();
+
+    let pairs = settings
+        .tool_pairs()
+        .into_iter()
+        .map(|(first, other)| (settings.tools[first].name.clone(), settings.tools[other].name.clone()))
+        .collect::<Vec<_>>();
+    let run_info = RunInfo {
+        mode: "compare",
+        tools: &tools,
+        pairs: &pairs,
+        baseline,
+        update_baseline,
+    };
+    report_results(settings, run_info, &results, &report_images);
+}
+
+// Describes which tools and pairs of tools were used in run, to be able to create reports
+pub struct RunInfo<'a> {
+    pub mode: &'a str,
+    pub tools: &'a [&'a ToolSettings],
+    pub pairs: &'a [(String, String)],
+    pub baseline: Option<Baseline>,
+    pub update_baseline: bool,
+}
+
+// Loaded before checking files, to not waste time when it is invalid
+pub fn load_baseline(settings: &Settings) -> Option<Baseline> {
+    if settings.baseline_file.is_empty() {
+        return None;
+    }
+    match Baseline::load(&settings.baseline_file) {
+        Ok(t) => Some(t),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+pub fn collect_files_to_check(settings: &Settings) -> Vec<String> {
+    let mut files_to_check = find_files(settings, ".svg");
+    assert!(!files_to_check.is_empty());
+
+    if settings.limit_files != 0 {
+        files_to_check.truncate(settings.limit_files);
+    }
+    files_to_check
+}
+
+// When resuming, files copied in previous run are kept, because their results are reused
+pub fn prepare_output_folders(settings: &Settings, resume: bool) {
+    // Remove output files if exists
+    if settings.remove_files_from_output_folder_at_start && !resume {
+        let _ = fs::remove_dir_all(&settings.output_folder);
+        let _ = fs::remove_dir_all(&settings.problematic_files_path);
+        let _ = fs::remove_dir_all(&settings.ignored_files_path);
+    }
+    let _ = fs::create_dir_all(&settings.output_folder);
+    let _ = fs::create_dir_all(&settings.problematic_files_path);
+    let _ = fs::create_dir_all(&settings.ignored_files_path);
+}
+
+// Returns results of all files and images for html report, by path of file
+// Files with results saved in `results_log` in previous run are not checked again
+pub fn check_files<F>(
+    settings: &Settings,
+    files_to_check: &[String],
+    results_log: &ResultsLog,
+    check_file: F,
+) -> (Vec<FileResult>, HashMap<String, ReportImages>)
+where
+    F: Fn(&str, &RenderJob) -> FileResult + Sync,
+{
+    let atomic: AtomicI32 = AtomicI32::new(0);
+    let results = Mutex::new(Vec::new());
+    let report_images = Mutex::new(HashMap::new());
+    stop_checking_on_interrupt();
+    files_to_check.par_iter().for_each(|source_file| {
+        if is_interrupted() {
+            return;
+        }
+        let number = atomic.fetch_add(1, Ordering::Relaxed);
+        if number % 100 == 0 {
+            println!("-- {}/{}", number, files_to_check.len());
+        }
+
+        let file_hash = results_log.input_hash(settings, source_file);
+        if let Some((file_result, file_report_images)) = results_log.find(settings, source_file, &file_hash) {
+            if let Some(file_report_images) = file_report_images {
+                report_images.lock().unwrap().insert(source_file.clone(), file_report_images);
+            }
+            results.lock().unwrap().push(file_result);
+            return;
+        }
+
+        // Renders are removed when job is dropped, so after saving renders shown in report
+        let job = RenderJob::create();
+        let file_result = check_file(source_file, &job);
+        let file_report_images = if settings.save_html_report {
+            save_report_images(settings, &job, &file_result)
+        } else {
+            None
+        };
+        save_file_result(settings, &file_result);
+        results_log.append(settings, &file_hash, &file_result, file_report_images.as_ref());
+        if let Some(file_report_images) = file_report_images {
+            report_images.lock().unwrap().insert(source_file.clone(), file_report_images);
+        }
+        results.lock().unwrap().push(file_result);
+    });
+    results_log.print_reused_results();
+    let results = results.into_inner().unwrap();
+    print_interrupted(results.len(), files_to_check.len());
+    (results, report_images.into_inner().unwrap())
+}
+
+pub fn report_results(settings: &Settings, run_info: RunInfo, results: &[FileResult], report_images: &HashMap<String, ReportImages>) {
+    let summary = Summary::new(results);
+    summary.print();
+    if settings.save_html_report {
+        save_html_report(settings, &summary, results, report_images);
+    }
+    if settings.save_json_results {
+        let tools = run_info
+            .tools
+            .iter()
+            .map(|tool| (tool.name.as_str(), tool.path.as_str(), tool.version_arguments.as_str()))
+            .collect::<Vec<_>>();
+        save_json_results(settings, &settings.output_folder, run_info.mode, &tools, &summary, results);
+    }
+    if settings.save_junit_report {
+        save_junit_report_for_pairs(&settings.output_folder, run_info.mode, run_info.pairs, results);
+    }
+    if settings.save_feature_statistics {
+        let feature_statistics = FeatureStatistics::new(results);
+        feature_statistics.print();
+        feature_statistics.save(&settings.output_folder);
+    }
+
+    if let Some(baseline) = run_info.baseline {
+        let changes = baseline.compare(settings, results);
+        changes.print();
+        if run_info.update_baseline {
+            baseline.updated(settings, results).save(&settings.baseline_file);
+            return;
+        }
+        if changes.new_failures.is_empty() {
+            println!("Not found any new failures");
+        } else {
+            eprintln!(
+                "POSSIBLE_PROBLEM - Found {} new failures, which are not in baseline",
+                changes.new_failures.len()
+            );
+            if settings.return_error_when_finding_invalid_files {
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let broken_items = summary.count(Category::Broken);
+    let problematic_items = summary.count(Category::Problematic);
+    if broken_items > 0 || problematic_items > 0 {
+        eprintln!("POSSIBLE_PROBLEM - Found {broken_items} files that looks different and {problematic_items} files that cannot be tested");
+        if settings.return_error_when_finding_invalid_files {
+            process::exit(1);
+        }
+    } else {
+        println!("Not found any problematic files");
+    }
+}
+
+pub fn check_file(settings: &Settings, render_cache: &RenderCache, job: &RenderJob, source_file: &str) -> FileResult {
+    let (file_result, images) = check_file_quietly(settings, render_cache, job, source_file);
+    for pair_result in file_result.pairs.iter().filter(|e| e.outcome.category() == Category::Broken) {
+        let tool_index = |name: &str| settings.tools.iter().position(|e| e.name == name).unwrap();
+        let (first, other) = (tool_index(&pair_result.first_tool), tool_index(&pair_result.other_tool));
+        let (Some(Some(first_image)), Some(Some(other_image))) = (images.get(first), images.get(other)) else {
+            continue;
+        };
+        let pair_output_folder = pair_output_folder(settings, &pair_result.first_tool, &pair_result.other_tool, source_file);
+        let _ = fs::create_dir_all(&pair_output_folder);
+        copy_to_file_name(&output_png(settings, job, source_file, &settings.tools[first]), &pair_output_folder);
+        copy_to_file_name(&output_png(settings, job, source_file, &settings.tools[other]), &pair_output_folder);
+        copy_to_file_name(source_file, &pair_output_folder);
+        let tool_names = (pair_result.first_tool.as_str(), pair_result.other_tool.as_str());
+        save_diff_images(settings, first_image, other_image, tool_names, source_file, &pair_output_folder);
+    }
+    file_result
+}
+
+// Same as `check_file`, but renders and diff images of broken pairs are not saved to output folder
+// Returns also loaded renders of tools, `None` when tool failed or file was ignored
+pub fn check_file_quietly(
+    settings: &Settings,
+    render_cache: &RenderCache,
+    job: &RenderJob,
+    source_file: &str,
+) -> (FileResult, Vec<Option<DynamicImage>>) {
+    let mut file_result = FileResult::new(source_file, original_file_name(settings, source_file));
+    let features = SvgFeatures::from_file(source_file);
+    file_result.features = features.names();
+    // Rule limited to some tools skips only them and pairs with them, so differences between other tools are still found
+    let mut input_ignore_rules = settings
+        .tools
+        .iter()
+        .map(|tool| find_input_ignore_rule(settings, "compare", &tool.name, &features))
+        .collect::<Vec<_>>();
+    if input_ignore_rules.iter().all(Option::is_some) {
+        if let Some(ignored) = input_ignore_rules.swap_remove(0) {
+            file_result.ignore(ignored);
+        }
+        return (file_result, Vec::new());
+    }
+
+    let output_pngs = settings
+        .tools
+        .iter()
+        .map(|tool| output_png(settings, job, source_file, tool))
+        .collect::<Vec<_>>();
+
+    let svg_hash = render_cache.svg_hash(source_file);
+    file_result.tools = settings
+        .tools
+        .iter()
+        .zip(&output_pngs)
+        .zip(input_ignore_rules)
+        .map(|((tool, output_png), ignored)| match ignored {
+            Some(ignored) => ToolResult::ignored(&tool.name, ignored),
+            None if settings.ignore_conversion_step => ToolResult::skipped(&tool.name),
+            None => render_with_cache(settings, render_cache, tool, source_file, &svg_hash, output_png),
+        })
+        .collect();
+    for tool_result in file_result.tools.iter_mut().filter(|e| e.outcome.category() == Category::Problematic) {
+        if let Some(ignored) = find_output_ignore_rule(settings, "compare", &tool_result.tool, &tool_result.output) {
+            tool_result.ignore(ignored);
+        }
+    }
+
+    if settings.ignore_similarity_checking_step {
+        return (file_result, Vec::new());
+    }
+
+    // Each image is loaded only once, even if it is compared with multiple other images
+    let mut images = Vec::new();
+    for (tool_result, output_png) in file_result.tools.iter_mut().zip(&output_pngs) {
+        if !tool_result.outcome.is_success() {
+            images.push(None);
+            continue;
+        }
+        match load_image(output_png) {
+            Ok(image) => images.push(Some(image)),
+            Err(outcome) => {
+                println!("Failed to open {output_png}, reason {outcome} (Probably app failed to generate png file)");
+                tool_result.outcome = outcome;
+                images.push(None);
+            }
+        }
+    }
+
+    for (first, other) in settings.tool_pairs() {
+        let (Some(first_image), Some(other_image)) = (&images[first], &images[other]) else {
+            continue;
+        };
+        let (outcome, metrics) = compare_images(first_image, other_image, settings);
+        if let Outcome::SizeMismatch { .. } = outcome {
+            println!("Ignored images {} and {} - {outcome}", output_pngs[first], output_pngs[other]);
+        }
+        file_result.pairs.push(PairResult {
+            first_tool: settings.tools[first].name.clone(),
+            other_tool: settings.tools[other].name.clone(),
+            outcome,
+            metrics,
+        });
+    }
+
+    (file_result, images)
+}
+
+// Copies input files to folders matching outcome of each tool
+pub fn save_file_result(settings: &Settings, file_result: &FileResult) {
+    let source_file = &file_result.file;
+    if file_result.ignore_reason.is_some() || file_result.outcomes().any(|e| e.category() == Category::Ignored) {
+        let ignored_folder = mirrored_output_folder(settings, &settings.ignored_files_path, source_file);
+        let _ = fs::create_dir_all(&ignored_folder);
+        copy_to_file_name(source_file, &ignored_folder);
+    }
+    if file_result.ignore_reason.is_some() {
+        return;
+    }
+
+    for tool_result in &file_result.tools {
+        if tool_result.outcome.category() == Category::Problematic {
+            let problematic_folder = save_problematic_file(settings, &tool_result.tool, source_file);
+            let file_stem = Path::new(source_file).file_stem().unwrap().to_string_lossy();
+            let _ = fs::write(
+                format!("{problematic_folder}/{file_stem}.txt"),
+                format!("STATUS: {}\n{}", tool_result.outcome, tool_result.output),
+            );
+        }
+    }
+    for pair_result in &file_result.pairs {
+        if let Some(metrics) = &pair_result.metrics {
+            if pair_result.outcome.category() == Category::Broken {
+                println!(
+                    "{source_file} - {} vs {} - {} ({metrics})",
+                    pair_result.first_tool, pair_result.other_tool, pair_result.outcome
+                );
+            }
+        }
+        if pair_result.outcome.category() == Category::Problematic {
+            let name = format!("{}_vs_{}", pair_result.first_tool, pair_result.other_tool);
+            save_problematic_file(settings, &name, source_file);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::{exit, Command};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use rayon::prelude::*;
+
+use crate::common::{mirrored_output_folder, original_file_name};
+use crate::find_files;
+use crate::fingerprint::tool_fingerprint;
+use crate::ignore_rules::find_output_ignore_rule;
+use crate::interrupt::{is_interrupted, print_interrupted, stop_checking_on_interrupt};
+use crate::json_report::save_json_results;
+use crate::junit_report::save_junit_report_for_tool;
+use crate::outcome::{Category, FileResult, Outcome, Summary, ToolResult};
+use crate::results_log::ResultsLog;
+use crate::runner::{run_command, ProcessLimits};
+use crate::sanitizer::{group_crashes, save_crash_groups};
+use crate::scratch::RenderJob;
+use crate::setting::Settings;
+
+// Mode in which each file is only opened by single tool, to find files that crash, leak or time out in it
+pub struct CrashMode<'a> {
+    // Name of mode, also used as name of tool in results
+    pub name: &'a str,
+    pub tool_path: &'a str,
+    // Passed after checked file
+    pub arguments: &'a [&'a str],
+    // e.g. `.svg`
+    pub extension: &'a str,
+    // Failed files are copied here, together with output of tool
+    pub output_folder: &'a str,
+}
+
+pub fn find_crashes(settings: &Settings, mode: &CrashMode, resume: bool) {
+    let files_to_check = find_files(settings, mode.extension);
+    assert!(!files_to_check.is_empty());
+
+    // When resuming, files copied in previous run are kept, because their results are reused
+    if !resume {
+        let _ = fs::remove_dir_all(mode.output_folder);
+    }
+    let _ = fs::create_dir(mode.output_folder);
+
+    let atomic_counter: AtomicI32 = AtomicI32::new(0);
+    let all_files = files_to_check.len();
+    let limits = ProcessLimits::from_settings(settings);
+    let fingerprints = BTreeMap::from([(
+        mode.name.to_string(),
+        tool_fingerprint(settings, mode.tool_path, &mode.arguments.join(" ")),
+    )]);
+    let results_log = ResultsLog::open(settings, mode.name, &fingerprints, resume);
+    stop_checking_on_interrupt();
+    let results = files_to_check
+        .into_par_iter()
+        .filter(|_| !is_interrupted())
+        .map(|e| {
+            let number = atomic_counter.fetch_add(1, Ordering::Relaxed);
+            if number % 100 == 0 {
+                println!("-- {}/{} - {}", number, all_files, mode.name.to_uppercase());
+            }
+            let file_hash = results_log.input_hash(settings, &e);
+            if let Some((file_result, _)) = results_log.find(settings, &e, &file_hash) {
+                return file_result;
+            }
+            let file_result = check_file(settings, mode, &limits, &e);
+            results_log.append(settings, &file_hash, &file_result, None);
+            file_result
+        })
+        .collect::<Vec<_>>();
+    results_log.print_reused_results();
+    print_interrupted(results.len(), all_files);
+
+    let summary = Summary::new(&results);
+    summary.print();
+    if settings.save_json_results {
+        let tools = [(mode.name, mode.tool_path, "--version")];
+        save_json_results(settings, mode.output_folder, mode.name, &tools, &summary, &results);
+    }
+    if settings.save_junit_report {
+        save_junit_report_for_tool(mode.output_folder, mode.name, &results);
+    }
+    let crash_groups = group_crashes(&results, settings.crash_signature_frames);
+    save_crash_groups(mode.output_folder, &crash_groups);
+    let broken_files_number = summary.count(Category::Problematic);
+    if broken_files_number > 0 {
+        eprintln!(
+            "POSSIBLE_PROBLEM - Found {broken_files_number} files that cannot be tested with {} due crashes/leaks/timeouts",
+            mode.name
+        );
+    }
+
+    exit(0);
+}
+
+fn copy_broken_file(settings: &Settings, mode: &CrashMode, file: &str, output: &str) {
+    let path = Path::new(file);
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    let file_stem = path.file_stem().unwrap().to_str().unwrap();
+    let broken_files_path = mirrored_output_folder(settings, mode.output_folder, file);
+
+    fs::create_dir_all(&broken_files_path).unwrap();
+    let _ = fs::copy(file, format!("{broken_files_path}/{file_name}"));
+    fs::write(format!("{broken_files_path}/{file_stem}.txt"), output).unwrap();
+}
+
+fn check_file(settings: &Settings, mode: &CrashMode, limits: &ProcessLimits, file: &str) -> FileResult {
+    // Output is saved beside input file, so copy of file in job folder is used, which is removed with everything created there
+    let job = RenderJob::create();
+    let mut file_result = FileResult::new(file, original_file_name(settings, file));
+    let output = match run_command(Command::new(mode.tool_path).arg(job.copy_source(file)).args(mode.arguments), limits) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to run {} on {file}, reason {e}", mode.name);
+            file_result.tools.push(ToolResult::failed_to_run(mode.name, &e));
+            return file_result;
+        }
+    };
+    let outcome = Outcome::from_process(&output);
+    if outcome.is_success() {
+        file_result.tools.push(ToolResult {
+            tool: mode.name.to_string(),
+            outcome,
+            exit_code: output.status.exit_code(),
+            duration: Some(output.duration),
+            output: String::new(),
+        });
+        return file_result;
+    }
+    let all = format!("STATUS: {outcome}\n{}", output.all_output());
+
+    if let Some(ignored) = find_output_ignore_rule(settings, mode.name, mode.name, &all) {
+        file_result.ignore(ignored);
+    }
+    if file_result.ignore_reason.is_none() {
+        println!("{}({})\n{}\n\n", file, (all.len()), all);
+        copy_broken_file(settings, mode, file, &all);
+    }
+    file_result.tools.push(ToolResult {
+        tool: mode.name.to_string(),
+        outcome,
+        exit_code: output.status.exit_code(),
+        duration: Some(output.duration),
+        output: all,
+    });
+    file_result
+}
@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::setting::Settings;
+
+const DIFF_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const BACKGROUND_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const TEXT_COLOR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+// Visible part of not changed pixels, rest is filled with white
+const DIMMED_IMAGE_OPACITY: f32 = 0.2;
+
+const GAP: u32 = 4;
+const FONT_SCALE: u32 = 2;
+const LABEL_HEIGHT: u32 = FONT_HEIGHT * FONT_SCALE + 2 * GAP;
+
+// Images must be loaded with `load_image` and have same size
+pub fn save_diff_images(
+    settings: &Settings,
+    first_image: &DynamicImage,
+    other_image: &DynamicImage,
+    tool_names: (&str, &str),
+    source_file: &str,
+    output_folder: &str,
+) {
+    if !settings.save_diff_images && !settings.save_composite_images {
+        return;
+    }
+    let first_rgba = first_image.as_rgba8().expect("Image should be converted to rgba8 when loading");
+    let other_rgba = other_image.as_rgba8().expect("Image should be converted to rgba8 when loading");
+    let diff_image = create_diff_image(first_rgba, other_rgba, settings.pixel_tolerance);
+
+    let file_stem = Path::new(source_file).file_stem().unwrap().to_string_lossy();
+    if settings.save_diff_images {
+        let diff_file = format!("{output_folder}/{file_stem}_diff.png");
+        if let Err(e) = diff_image.save(&diff_file) {
+            eprintln!("Failed to save {diff_file} - {e}");
+        }
+    }
+    if settings.save_composite_images {
+        let composite_file = format!("{output_folder}/{file_stem}_composite.png");
+        let composite = create_composite_image(&[(tool_names.0, first_rgba), (tool_names.1, other_rgba), ("diff", &diff_image)]);
+        if let Err(e) = composite.save(&composite_file) {
+            eprintln!("Failed to save {composite_file} - {e}");
+        }
+    }
+}
+
+// Differing pixels are red, all other pixels are dimmed grayscale version of first image
+pub fn create_diff_image(first_image: &RgbaImage, second_image: &RgbaImage, pixel_tolerance: u8) -> RgbaImage {
+    let mut diff_image = RgbaImage::new(first_image.width(), first_image.height());
+    for ((first_pixel, second_pixel), diff_pixel) in first_image.pixels().zip(second_image.pixels()).zip(diff_image.pixels_mut()) {
+        let differs = (0..3).any(|channel| first_pixel.0[channel].abs_diff(second_pixel.0[channel]) > pixel_tolerance);
+        *diff_pixel = if differs {
+            DIFF_COLOR
+        } else {
+            let luma = 0.299 * f32::from(first_pixel.0[0]) + 0.587 * f32::from(first_pixel.0[1]) + 0.114 * f32::from(first_pixel.0[2]);
+            let dimmed = (luma * DIMMED_IMAGE_OPACITY + 255.0 * (1.0 - DIMMED_IMAGE_OPACITY)) as u8;
+            Rgba([dimmed, dimmed, dimmed, 255])
+        };
+    }
+    diff_image
+}
+
+// Places images next to each other, with label above each of them
+pub fn create_composite_image(images: &[(&str, &RgbaImage)]) -> RgbaImage {
+    let width = images.iter().map(|(_, image)| image.width() + GAP).sum::<u32>() + GAP;
+    let height = images.iter().map(|(_, image)| image.height()).max().unwrap_or_default() + LABEL_HEIGHT + GAP;
+    let mut composite = RgbaImage::from_pixel(width, height, BACKGROUND_COLOR);
+
+    let mut x = GAP;
+    for (label, image) in images {
+        draw_text(&mut composite, label, x, GAP, image.width());
+        image::imageops::overlay(&mut composite, *image, i64::from(x), i64::from(LABEL_HEIGHT));
+        x += image.width() + GAP;
+    }
+    composite
+}
+
+// Text that not fits in max_width is cut
+fn draw_text(image: &mut RgbaImage, text: &str, start_x: u32, start_y: u32, max_width: u32) {
+    let glyph_width = (FONT_WIDTH + 1) * FONT_SCALE;
+    for (idx, character) in text.chars().enumerate() {
+        let glyph_x = start_x + idx as u32 * glyph_width;
+        if glyph_x + FONT_WIDTH * FONT_SCALE > start_x + max_width {
+            break;
+        }
+        let glyph = glyph(character);
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..FONT_WIDTH {
+                if bits & (1 << (FONT_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                for dy in 0..FONT_SCALE {
+                    for dx in 0..FONT_SCALE {
+                        let x = glyph_x + column * FONT_SCALE + dx;
+                        let y = start_y + row as u32 * FONT_SCALE + dy;
+                        if x < image.width() && y < image.height() {
+                            image.put_pixel(x, y, TEXT_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+const FONT_WIDTH: u32 = 5;
+const FONT_HEIGHT: u32 = 7;
+
+// Simple 5x7 bitmap font, lowercase letters are drawn as uppercase
+fn glyph(character: char) -> [u8; FONT_HEIGHT as usize] {
+    match character.to_ascii_uppercase() {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ' ' => [0x00; FONT_HEIGHT as usize],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+
+use crate::outcome::{Category, FileResult};
+
+pub const FEATURE_STATISTICS_FILE: &str = "feature_statistics.csv";
+
+// Features used by less files, have too random failure rate to be shown in printed table
+const MIN_FILES_TO_PRINT: usize = 5;
+const PRINTED_FEATURES: usize = 20;
+
+#[derive(Default)]
+struct FeatureCounts {
+    files: usize,
+    broken: usize,
+    problematic: usize,
+    ignored: usize,
+}
+
+impl FeatureCounts {
+    fn add(&mut self, category: Category) {
+        self.files += 1;
+        match category {
+            Category::Passed => {}
+            Category::Ignored => self.ignored += 1,
+            Category::Broken => self.broken += 1,
+            Category::Problematic => self.problematic += 1,
+        }
+    }
+
+    // Ignored files are not checked, so are not counted
+    fn failure_rate(&self) -> f64 {
+        let checked = self.files - self.ignored;
+        if checked == 0 {
+            return 0.0;
+        }
+        (self.broken + self.problematic) as f64 / checked as f64
+    }
+}
+
+// Shows which features are used by files that fail more often than others
+pub struct FeatureStatistics {
+    all_files: FeatureCounts,
+    features: BTreeMap<String, FeatureCounts>,
+}
+
+impl FeatureStatistics {
+    pub fn new(results: &[FileResult]) -> Self {
+        let mut all_files = FeatureCounts::default();
+        let mut features: BTreeMap<String, FeatureCounts> = BTreeMap::new();
+        for file_result in results {
+            let category = file_result.category();
+            all_files.add(category);
+            for feature in &file_result.features {
+                features.entry(feature.clone()).or_default().add(category);
+            }
+        }
+        Self { all_files, features }
+    }
+
+    // Failure rate of feature divided by failure rate of all files, bigger than 1 means that feature fails more often
+    fn relative_rate(&self, counts: &FeatureCounts) -> f64 {
+        let baseline_rate = self.all_files.failure_rate();
+        if baseline_rate == 0.0 {
+            return 0.0;
+        }
+        counts.failure_rate() / baseline_rate
+    }
+
+    // Features sorted from the most failing
+    fn sorted_features(&self) -> Vec<(&String, &FeatureCounts)> {
+        let mut features = self.features.iter().collect::<Vec<_>>();
+        features.sort_by(|a, b| {
+            b.1.failure_rate()
+                .total_cmp(&a.1.failure_rate())
+                .then_with(|| b.1.files.cmp(&a.1.files))
+                .then_with(|| a.0.cmp(b.0))
+        });
+        features
+    }
+
+    pub fn print(&self) {
+        if self.features.is_empty() {
+            return;
+        }
+        println!(
+            "Features with the highest failure rate(used by at least {MIN_FILES_TO_PRINT} files), failure rate of all files - {:.1}%",
+            self.all_files.failure_rate() * 100.0
+        );
+        println!(
+            "{:<40} {:>7} {:>7} {:>11} {:>7} {:>8} {:>9}",
+            "feature", "files", "broken", "problematic", "ignored", "failure", "relative"
+        );
+        for (feature, counts) in self
+            .sorted_features()
+            .into_iter()
+            .filter(|(_, counts)| counts.files - counts.ignored >= MIN_FILES_TO_PRINT)
+            .take(PRINTED_FEATURES)
+        {
+            println!(
+                "{:<40} {:>7} {:>7} {:>11} {:>7} {:>7.1}% {:>8.2}x",
+                feature,
+                counts.files,
+                counts.broken,
+                counts.problematic,
+                counts.ignored,
+                counts.failure_rate() * 100.0,
+                self.relative_rate(counts)
+            );
+        }
+    }
+
+    // All features are saved, so they can be analyzed in any spreadsheet
+    pub fn save(&self, output_folder: &str) {
+        let mut content = "feature,files,broken,problematic,ignored,failure_rate,relative_rate\n".to_string();
+        for (feature, counts) in self.sorted_features() {
+            let _ = writeln!(
+                content,
+                "\"{}\",{},{},{},{},{:.4},{:.4}",
+                feature.replace('"', "\"\""),
+                counts.files,
+                counts.broken,
+                counts.problematic,
+                counts.ignored,
+                counts.failure_rate(),
+                self.relative_rate(counts)
+            );
+        }
+        let file_name = format!("{output_folder}/{FEATURE_STATISTICS_FILE}");
+        match fs::write(&file_name, content) {
+            Ok(()) => println!("Saved feature statistics to {file_name}"),
+            Err(e) => eprintln!("Failed to save feature statistics to {file_name} - {e}"),
+        }
+    }
+}
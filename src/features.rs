@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+// Features used in svg file, with number of occurrences:
+// - `<name` - element e.g. `<filter`, svg namespace prefix is skipped, so `<svg:filter` is also `<filter`
+// - `@name` - attribute e.g. `@transform` or `@xlink:href`, xlink and xml attributes written with other prefix are also reported with it e.g. `@x:href`
+// - `name` and `name:value` - css property from `style` attribute or `<style>` element e.g. `mix-blend-mode:multiply`
+// - `!ENTITY` - entity declared in DOCTYPE, `!invalid-xml` - file cannot be parsed, then features are found by simple scan of tags
+// Comments and CDATA are not treated as elements
+#[derive(Default)]
+pub struct SvgFeatures {
+    counts: BTreeMap<String, usize>,
+}
+
+impl SvgFeatures {
+    pub fn from_file(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::from_content(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn from_content(content: &str) -> Self {
+        let mut features = Self::default();
+        let document = match Document::parse_with_options(content, parsing_options()) {
+            Ok(t) => t,
+            Err(_) => {
+                features.add("!invalid-xml");
+                features.add_scanned_tags(content);
+                return features;
+            }
+        };
+        // Entities are already expanded by parser, so only their declarations can be found
+        if content[..document.root_element().range().start].contains("<!ENTITY") {
+            features.add("!ENTITY");
+        }
+
+        for node in document.descendants().filter(Node::is_element) {
+            let tag_name = node.tag_name();
+            features.add(&format!("<{}", qualified_name(node, tag_name.namespace(), tag_name.name())));
+            for attribute in node.attributes() {
+                let name = qualified_name(node, attribute.namespace(), attribute.name());
+                // Some tools recognize attributes only by usual prefix
+                if let Some(used_prefix) = attribute.namespace().and_then(|e| node.lookup_prefix(e)) {
+                    let used_name = format!("{used_prefix}:{}", attribute.name());
+                    if used_name != name {
+                        features.add(&format!("@{used_name}"));
+                    }
+                }
+                features.add(&format!("@{name}"));
+                if attribute.name() == "style" && attribute.namespace().is_none() {
+                    features.add_css_declarations(attribute.value());
+                }
+            }
+            if tag_name.name() == "style" {
+                for text in node.children().filter_map(|e| e.text()) {
+                    features.add_css_rules(text);
+                }
+            }
+        }
+        features
+    }
+
+    pub fn count(&self, feature: &str) -> usize {
+        self.counts.get(feature).copied().unwrap_or_default()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.counts.keys().cloned().collect()
+    }
+
+    // Used when file is not valid xml e.g. uses undeclared `xlink:` prefix, so namespaces are not resolved and prefixes are kept as written
+    fn add_scanned_tags(&mut self, content: &str) {
+        if content.contains("<!ENTITY") {
+            self.add("!ENTITY");
+        }
+        let mut rest = content;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            let skipped_block = [("!--", "-->"), ("![CDATA[", "]]>")].iter().find_map(|(opening, closing)| {
+                let block = rest.strip_prefix(opening)?;
+                Some(block.find(closing).map_or("", |end| &block[end + closing.len()..]))
+            });
+            if let Some(after_block) = skipped_block {
+                rest = after_block;
+                continue;
+            }
+            let tag_name = take_name(&mut rest);
+            if tag_name.is_empty() {
+                continue;
+            }
+            let tag_name = tag_name.strip_prefix("svg:").unwrap_or(tag_name);
+            self.add(&format!("<{tag_name}"));
+            loop {
+                rest = rest.trim_start();
+                let attribute_name = take_name(&mut rest);
+                if attribute_name.is_empty() {
+                    break;
+                }
+                let mut value = "";
+                if let Some(after_equals) = rest.trim_start().strip_prefix('=') {
+                    let after_equals = after_equals.trim_start();
+                    let quote = after_equals.chars().next().filter(|e| *e == '"' || *e == '\'');
+                    let Some(quote) = quote else {
+                        break;
+                    };
+                    let quoted = &after_equals[1..];
+                    let end = quoted.find(quote).unwrap_or(quoted.len());
+                    value = &quoted[..end];
+                    rest = quoted.get(end + 1..).unwrap_or("");
+                }
+                self.add(&format!("@{attribute_name}"));
+                if attribute_name == "style" {
+                    self.add_css_declarations(value);
+                }
+            }
+            if tag_name == "style" {
+                if let Some(text) = rest.split_once('>').map(|e| e.1) {
+                    self.add_css_rules(text.split("</").next().unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, feature: &str) {
+        *self.counts.entry(feature.to_string()).or_insert(0) += 1;
+    }
+
+    // Selectors are skipped, only declarations inside braces are used
+    fn add_css_rules(&mut self, css: &str) {
+        let css = remove_css_comments(css);
+        for block in css.split('}') {
+            if let Some((_, declarations)) = block.rsplit_once('{') {
+                self.add_css_declarations(declarations);
+            }
+        }
+    }
+
+    fn add_css_declarations(&mut self, declarations: &str) {
+        for declaration in declarations.split(';') {
+            let Some((property, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            let property = property.trim().to_lowercase();
+            if property.is_empty() {
+                continue;
+            }
+            let value = value
+                .trim()
+                .trim_end_matches("!important")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !value.is_empty() {
+                self.add(&format!("{property}:{}", value.to_lowercase()));
+            }
+            self.add(&property);
+        }
+    }
+}
+
+// Many svg files contains DOCTYPE, which is rejected by default
+pub fn parsing_options() -> ParsingOptions {
+    ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    }
+}
+
+// Prefix used in file may be different than usual one, so well known namespaces have always the same prefix
+fn qualified_name(node: Node, namespace: Option<&str>, name: &str) -> String {
+    let prefix = match namespace {
+        None | Some(SVG_NAMESPACE) => None,
+        Some(XLINK_NAMESPACE) => Some("xlink"),
+        Some(XML_NAMESPACE) => Some("xml"),
+        Some(namespace) => node.lookup_prefix(namespace),
+    };
+    match prefix {
+        Some(prefix) => format!("{prefix}:{name}"),
+        None => name.to_string(),
+    }
+}
+
+// Takes xml name from beginning of text, empty when text starts with other character
+fn take_name<'a>(text: &mut &'a str) -> &'a str {
+    let starts_with_name = text.starts_with(|e: char| e.is_alphabetic() || e == '_');
+    let end = if starts_with_name {
+        text.find(|e: char| !(e.is_alphanumeric() || matches!(e, '_' | ':' | '-' | '.')))
+            .unwrap_or(text.len())
+    } else {
+        0
+    };
+    let (name, rest) = text.split_at(end);
+    *text = rest;
+    name
+}
+
+fn remove_css_comments(css: &str) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        rest = rest[start..].find("*/").map_or("", |end| &rest[start + end + 2..]);
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_elements_attributes_and_css() {
+        let features = SvgFeatures::from_content(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+                <style>/* filter: none */ rect { mix-blend-mode: Multiply }</style>
+                <!-- <pattern -->
+                <svg:rect xmlns:svg="http://www.w3.org/2000/svg" style="fill: red !important"/>
+                <use xlink:href="#a"/>
+            </svg>"##,
+        );
+        assert_eq!(features.count("<svg"), 1);
+        assert_eq!(features.count("<rect"), 1);
+        assert_eq!(features.count("@xlink:href"), 1);
+        assert_eq!(features.count("@x:href"), 0);
+        assert_eq!(features.count("mix-blend-mode:multiply"), 1);
+        assert_eq!(features.count("fill:red"), 1);
+        assert_eq!(features.count("filter"), 0);
+        assert_eq!(features.count("<pattern"), 0);
+        assert_eq!(features.count("!invalid-xml"), 0);
+    }
+
+    #[test]
+    fn reports_unusual_prefix_of_xlink_attributes() {
+        let features =
+            SvgFeatures::from_content(r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:x="http://www.w3.org/1999/xlink"><use x:href="#a"/></svg>"##);
+        assert_eq!(features.count("@xlink:href"), 1);
+        assert_eq!(features.count("@x:href"), 1);
+    }
+
+    #[test]
+    fn finds_declared_entities() {
+        let features =
+            SvgFeatures::from_content(r##"<!DOCTYPE svg [<!ENTITY color "red">]><svg xmlns="http://www.w3.org/2000/svg" fill="&color;"/>"##);
+        assert_eq!(features.count("!ENTITY"), 1);
+        assert_eq!(features.count("@fill"), 1);
+    }
+
+    #[test]
+    fn scans_tags_of_invalid_xml() {
+        // `xlink` prefix is not declared
+        let features = SvgFeatures::from_content(
+            r##"<svg xmlns="http://www.w3.org/2000/svg"><!-- <text --><filter id='f'/><use xlink:href="#a" style="mix-blend-mode:multiply"/>
+                <style><![CDATA[ <image ]]></style><svg:pattern/></svg>"##,
+        );
+        assert_eq!(features.count("!invalid-xml"), 1);
+        assert_eq!(features.count("<svg"), 1);
+        assert_eq!(features.count("<filter"), 1);
+        assert_eq!(features.count("@id"), 1);
+        assert_eq!(features.count("@xlink:href"), 1);
+        assert_eq!(features.count("mix-blend-mode:multiply"), 1);
+        assert_eq!(features.count("<pattern"), 1);
+        assert_eq!(features.count("<text"), 0);
+        assert_eq!(features.count("<image"), 0);
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt::Write;
+use std::fs;
+use std::process::{Command, Stdio};
+
+use sha2::{Digest, Sha256};
+
+use crate::common::resolve_program;
+use crate::setting::Settings;
+
+// Hex encoded sha256 of file content, None when file cannot be read
+pub fn file_hash(path: &str) -> Option<String> {
+    fs::read(path).ok().map(|content| hash_bytes(&content))
+}
+
+pub fn hash_bytes(content: &[u8]) -> String {
+    to_hex(&Sha256::digest(content))
+}
+
+// Changes when binary of tool or shared libraries used by it are rebuilt, or arguments and limits that may change its results are different
+// Tools from PATH are searched the same way as when running them
+pub fn tool_fingerprint(settings: &Settings, tool_path: &str, arguments: &str) -> String {
+    let binary_path = resolve_program(tool_path);
+    let mut hasher = Sha256::new();
+    match fs::read(&binary_path).ok() {
+        Some(content) => hasher.update(&content),
+        None => hasher.update(tool_path.as_bytes()),
+    }
+    for library in shared_libraries(&binary_path) {
+        hasher.update([0]);
+        hasher.update(library.as_bytes());
+        if let Ok(content) = fs::read(&library) {
+            hasher.update(&content);
+        }
+    }
+    let items = [
+        arguments.to_string(),
+        settings.px_size_of_generated_file.to_string(),
+        settings.timeout.to_string(),
+        settings.memory_limit.to_string(),
+        settings.cpu_time_limit.to_string(),
+    ];
+    for item in items {
+        // Separator prevents treating `ab`,`c` and `a`,`bc` as the same items
+        hasher.update([0]);
+        hasher.update(item.as_bytes());
+    }
+    to_hex(&hasher.finalize())
+}
+
+// Libraries found by `ldd`, so e.g. thorvg tool linked with rebuilt `libthorvg.so` gets new fingerprint, even when its binary is the same
+// Libraries opened with `dlopen` and files used by wrapper scripts are not found, empty when `ldd` is not available or file is not binary
+fn shared_libraries(binary_path: &str) -> Vec<String> {
+    let Ok(output) = Command::new("ldd").arg(binary_path).stdin(Stdio::null()).output() else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    let mut libraries = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            // e.g. `libthorvg.so.1 => /usr/lib/libthorvg.so.1 (0x7f...)` or `/lib64/ld-linux-x86-64.so.2 (0x7f...)`
+            let path = line.rsplit("=>").next()?.split_whitespace().next()?;
+            path.starts_with('/').then(|| path.to_string())
+        })
+        .collect::<Vec<_>>();
+    libraries.sort_unstable();
+    libraries.dedup();
+    libraries
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_shared_libraries_of_binary() {
+        let libraries = shared_libraries(&resolve_program("sh"));
+        assert!(libraries.iter().any(|e| e.contains("libc.so")), "{libraries:?}");
+        assert!(libraries.iter().all(|e| e.starts_with('/')), "{libraries:?}");
+    }
+
+    #[test]
+    fn finds_no_shared_libraries_of_script() {
+        assert!(shared_libraries(concat!(env!("CARGO_MANIFEST_DIR"), "/settings.toml")).is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::common::{original_file_name, relative_file_name};
+use crate::compare::{check_files, collect_files_to_check, load_baseline, prepare_output_folders, report_results, RunInfo};
+use crate::diff_image::save_diff_images;
+use crate::features::SvgFeatures;
+use crate::ignore_rules::{find_input_ignore_rule, find_output_ignore_rule};
+use crate::image_comparison::{compare_images, copy_to_file_name, load_image, pair_output_folder};
+use crate::outcome::{Category, FileResult, Outcome, PairResult, ToolResult};
+use crate::render_cache::RenderCache;
+use crate::results_log::ResultsLog;
+use crate::scratch::RenderJob;
+use crate::setting::{Settings, ToolSettings};
+use crate::svg_2_png::{output_png, render_with_cache, render_with_tool};
+
+// Used instead of name of second tool in results
+pub const GOLDEN_NAME: &str = "golden";
+
+pub fn test_golden(settings: &Settings, update_goldens: bool, update_baseline: bool, resume: bool) {
+    let tool = settings.golden_tool();
+    let files_to_check = collect_files_to_check(settings);
+    if update_goldens {
+        update_golden_files(settings, tool, &files_to_check);
+        return;
+    }
+
+    let baseline = load_baseline(settings);
+    prepare_output_folders(settings, resume);
+
+    // Reference image is part of key, so results are not reused when reference image was changed
+    let render_cache = RenderCache::open(settings, &[tool]);
+    let results_log = ResultsLog::open(settings, "golden", render_cache.fingerprints(), resume).with_reference_file(golden_file);
+    let (results, report_images) = check_files(settings, &files_to_check, &results_log, |source_file, job| {
+        check_file(settings, &render_cache, job, tool, source_file)
+    });
+    render_cache.print_statistics();
+
+    let pairs = [(tool.name.clone(), GOLDEN_NAME.to_string())];
+    let run_info = RunInfo {
+        mode: "golden",
+        tools: &[tool],
+        pairs: &pairs,
+        baseline,
+        update_baseline,
+    };
+    report_results(settings, run_info, &results, &report_images);
+}
+
+// Reference png files keep same relative paths as svg files
+fn golden_file(settings: &Settings, source_file: &str) -> String {
+    let relative_png = Path::new(&relative_file_name(settings, source_file)).with_extension("png");
+    Path::new(&settings.golden_folder).join(relative_png).to_string_lossy().to_string()
+}
+
+fn check_file(settings: &Settings, render_cache: &RenderCache, job: &RenderJob, tool: &ToolSettings, source_file: &str) -> FileResult {
+    let mut file_result = FileResult::new(source_file, original_file_name(settings, source_file));
+    let features = SvgFeatures::from_file(source_file);
+    file_result.features = features.names();
+    if let Some(ignored) = find_input_ignore_rule(settings, "golden", &tool.name, &features) {
+        file_result.ignore(ignored);
+        return file_result;
+    }
+
+    let output_png = output_png(settings, job, source_file, tool);
+    let mut tool_result = if settings.ignore_conversion_step {
+        ToolResult::skipped(&tool.name)
+    } else {
+        render_with_cache(
+            settings,
+            render_cache,
+            tool,
+            source_file,
+            &render_cache.svg_hash(source_file),
+            &output_png,
+        )
+    };
+    if !tool_result.outcome.is_success() {
+        if let Some(ignored) = find_output_ignore_rule(settings, "golden", &tool.name, &tool_result.output) {
+            file_result.ignore(ignored);
+        }
+    }
+    if !tool_result.outcome.is_success() || settings.ignore_similarity_checking_step {
+        file_result.tools.push(tool_result);
+        return file_result;
+    }
+
+    let image = match load_image(&output_png) {
+        Ok(t) => t,
+        Err(outcome) => {
+            println!("Failed to open {output_png}, reason {outcome} (Probably app failed to generate png file)");
+            tool_result.outcome = outcome;
+            file_result.tools.push(tool_result);
+            return file_result;
+        }
+    };
+    file_result.tools.push(tool_result);
+
+    let golden_file = golden_file(settings, source_file);
+    let golden_image = if Path::new(&golden_file).is_file() {
+        load_image(&golden_file)
+    } else {
+        Err(Outcome::MissingReference { path: golden_file.clone() })
+    };
+    let (outcome, metrics) = match &golden_image {
+        Ok(golden_image) => compare_images(&image, golden_image, settings),
+        Err(outcome) => (outcome.clone(), None),
+    };
+
+    if let (Ok(golden_image), Category::Broken) = (&golden_image, outcome.category()) {
+        let pair_output_folder = pair_output_folder(settings, &tool.name, GOLDEN_NAME, source_file);
+        let _ = fs::create_dir_all(&pair_output_folder);
+        copy_to_file_name(&output_png, &pair_output_folder);
+        copy_to_file_name(source_file, &pair_output_folder);
+        let file_stem = Path::new(source_file).file_stem().unwrap().to_string_lossy();
+        let _ = fs::copy(&golden_file, format!("{pair_output_folder}/{file_stem}_{GOLDEN_NAME}.png"));
+        save_diff_images(
+            settings,
+            &image,
+            golden_image,
+            (&tool.name, GOLDEN_NAME),
+            source_file,
+            &pair_output_folder,
+        );
+    }
+    file_result.pairs.push(PairResult {
+        first_tool: tool.name.clone(),
+        other_tool: GOLDEN_NAME.to_string(),
+        outcome,
+        metrics,
+    });
+    file_result
+}
+
+// Current renders are accepted as new reference images, files that failed to render are left untouched
+fn update_golden_files(settings: &Settings, tool: &ToolSettings, files_to_check: &[String]) {
+    let updated = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    files_to_check.par_iter().for_each(|source_file| {
+        if find_input_ignore_rule(settings, "golden", &tool.name, &SvgFeatures::from_file(source_file)).is_some() {
+            return;
+        }
+        let job = RenderJob::create();
+        let output_png = job.output_png(source_file, tool);
+        let tool_result = render_with_tool(settings, tool, source_file, &output_png);
+        if !tool_result.outcome.is_success() {
+            failed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let golden_file = golden_file(settings, source_file);
+        if let Some(parent) = Path::new(&golden_file).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::copy(&output_png, &golden_file) {
+            Ok(_) => {
+                updated.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                eprintln!("Failed to copy {output_png} to {golden_file} - {e}");
+                failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    println!(
+        "Updated {} reference png files in {}, {} files failed",
+        updated.into_inner(),
+        settings.golden_folder,
+        failed.into_inner()
+    );
+}
@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{mirrored_output_folder, png_file_name, relative_file_name};
+use crate::image_comparison::pair_output_folder;
+use crate::outcome::{Category, FileResult, Summary};
+use crate::scratch::RenderJob;
+use crate::setting::Settings;
+use crate::svg_2_png::output_png;
+
+pub const HTML_REPORT_FILE: &str = "index.html";
+// Folder inside output folder, where renders shown in report are saved, because job folders are removed just after checking file
+const REPORT_IMAGES_FOLDER: &str = "report_images";
+
+// Tool output may be really long e.g. with sanitizer reports, so only its beginning is shown
+const MAX_OUTPUT_CHARS: usize = 3000;
+
+// Renders of file shown in report, saved when file is checked, so report can be created at the end without keeping images in memory
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ReportImages {
+    // Path of render of each tool which rendered file
+    pub renders: BTreeMap<String, String>,
+}
+
+// Must be called before job with renders is dropped, returns None when file passed, so it is not shown in report
+pub fn save_report_images(settings: &Settings, job: &RenderJob, file_result: &FileResult) -> Option<ReportImages> {
+    if file_result.category() == Category::Passed {
+        return None;
+    }
+    let mut report_images = ReportImages::default();
+    for tool_result in file_result.tools.iter().filter(|e| e.outcome.is_success()) {
+        let Some(tool) = settings.tools.iter().find(|e| e.name == tool_result.tool) else {
+            continue;
+        };
+        let images_folder = format!("{}/{REPORT_IMAGES_FOLDER}/{}", settings.output_folder, tool.name);
+        let images_folder = mirrored_output_folder(settings, &images_folder, &file_result.file);
+        let _ = fs::create_dir_all(&images_folder);
+        let render = format!("{images_folder}/{}", png_file_name(&file_result.file, &tool.png_name_ending));
+        if fs::copy(output_png(settings, job, &file_result.file, tool), &render).is_ok() {
+            report_images.renders.insert(tool.name.clone(), render);
+        }
+    }
+    Some(report_images)
+}
+
+fn create_report_entry(settings: &Settings, file_result: &FileResult, report_images: &ReportImages) -> String {
+    let category = file_result.category();
+    let source_file = &file_result.file;
+    let file_name = relative_file_name(settings, source_file);
+    let file_stem = Path::new(source_file).file_stem().unwrap().to_string_lossy();
+
+    let mut tools = Vec::new();
+    let mut outcomes = Vec::new();
+    let mut max_hash_difference = -1;
+    let mut max_differing_pixels = -1;
+    let mut min_ssim = 2.0;
+    for tool_result in file_result.tools.iter().filter(|e| !e.outcome.is_success()) {
+        tools.push(tool_result.tool.as_str());
+        outcomes.push(tool_result.outcome.kind());
+    }
+    for pair_result in file_result.pairs.iter().filter(|e| !e.outcome.is_success()) {
+        tools.push(pair_result.first_tool.as_str());
+        tools.push(pair_result.other_tool.as_str());
+        outcomes.push(pair_result.outcome.kind());
+        if let Some(metrics) = &pair_result.metrics {
+            max_hash_difference = max_hash_difference.max(i64::from(metrics.hash_difference));
+            max_differing_pixels = max_differing_pixels.max(metrics.differing_pixels as i64);
+            min_ssim = f64::min(min_ssim, metrics.ssim);
+        }
+    }
+    if file_result.ignore_reason.is_some() {
+        outcomes.push("ignored");
+    }
+    tools.sort_unstable();
+    tools.dedup();
+    outcomes.sort_unstable();
+    outcomes.dedup();
+
+    let mut entry = String::new();
+    let _ = writeln!(
+        entry,
+        r#"<div class="file" data-name="{}" data-category="{category:?}" data-tools=" {} " data-outcomes=" {} " data-hash="{max_hash_difference}" data-pixels="{max_differing_pixels}" data-ssim="{min_ssim}">"#,
+        escape_html(&file_name),
+        escape_html(&tools.join(" ")),
+        outcomes.join(" ")
+    );
+    let _ = writeln!(entry, "<h2>{} - {category:?}</h2>", escape_html(&file_name));
+    let _ = writeln!(
+        entry,
+        "<div class=\"images\">{}</div>",
+        image_figure("svg", &embedded_image(source_file, "image/svg+xml"))
+    );
+    if let Some(ignore_reason) = &file_result.ignore_reason {
+        let _ = writeln!(entry, "<p>Ignored - {}</p>", escape_html(ignore_reason));
+    }
+
+    if !file_result.tools.is_empty() {
+        let _ = writeln!(entry, "<h3>Tools</h3><div class=\"images\">");
+        for tool_result in &file_result.tools {
+            let caption = format!("{} - {}", tool_result.tool, tool_result.outcome);
+            let mut content = match report_images.renders.get(&tool_result.tool) {
+                Some(render) => embedded_image(render, "image/png"),
+                None => String::new(),
+            };
+            if !tool_result.outcome.is_success() && !tool_result.output.is_empty() {
+                let output = tool_result.output.chars().take(MAX_OUTPUT_CHARS).collect::<String>();
+                let _ = write!(content, "<pre>{}</pre>", escape_html(&output));
+            }
+            entry.push_str(&image_figure(&caption, &content));
+        }
+        entry.push_str("</div>\n");
+    }
+
+    let failed_pairs = file_result.pairs.iter().filter(|e| !e.outcome.is_success()).collect::<Vec<_>>();
+    if !failed_pairs.is_empty() {
+        let _ = writeln!(entry, "<h3>Differences</h3><div class=\"images\">");
+        for pair_result in failed_pairs {
+            let pair_output_folder = pair_output_folder(settings, &pair_result.first_tool, &pair_result.other_tool, source_file);
+            let diff_file = format!("{pair_output_folder}/{file_stem}_diff.png");
+            let mut content = embedded_image(&diff_file, "image/png");
+            let _ = write!(content, "<p>{}</p>", escape_html(&pair_result.outcome.to_string()));
+            if let Some(metrics) = &pair_result.metrics {
+                let _ = write!(content, "<p>{metrics}</p>");
+            }
+            entry.push_str(&image_figure(
+                &format!("{} vs {}", pair_result.first_tool, pair_result.other_tool),
+                &content,
+            ));
+        }
+        entry.push_str("</div>\n");
+    }
+    entry.push_str("</div>\n");
+    entry
+}
+
+// `report_images` contains only files that not passed, by path of checked file
+pub fn save_html_report(settings: &Settings, summary: &Summary, results: &[FileResult], report_images: &HashMap<String, ReportImages>) {
+    let mut outcomes = summary
+        .outcomes
+        .keys()
+        .map(|(_, kind)| *kind)
+        .filter(|e| *e != "success")
+        .collect::<Vec<_>>();
+    if summary.count(Category::Ignored) > 0 {
+        outcomes.push("ignored");
+    }
+    outcomes.sort_unstable();
+    outcomes.dedup();
+
+    let mut report = String::new();
+    report.push_str(REPORT_HEADER);
+    let _ = writeln!(
+        report,
+        "<p>Checked {} files - broken {}, problematic {}, ignored {}</p>",
+        summary.files,
+        summary.count(Category::Broken),
+        summary.count(Category::Problematic),
+        summary.count(Category::Ignored)
+    );
+    report.push_str("<div class=\"controls\">\n<label>Tool <select id=\"tool\"><option value=\"\">All</option>");
+    for tool in &settings.tools {
+        let _ = write!(report, "<option>{}</option>", escape_html(&tool.name));
+    }
+    report.push_str("</select></label>\n<label>Outcome <select id=\"outcome\"><option value=\"\">All</option>");
+    for outcome in outcomes {
+        let _ = write!(report, "<option>{outcome}</option>");
+    }
+    report.push_str("</select></label>\n");
+    report.push_str(REPORT_SORT_CONTROLS);
+    report.push_str("</div>\n<div id=\"files\">\n");
+    for file_result in results {
+        if let Some(report_images) = report_images.get(&file_result.file) {
+            report.push_str(&create_report_entry(settings, file_result, report_images));
+        }
+    }
+    report.push_str("</div>\n");
+    report.push_str(REPORT_FOOTER);
+
+    let report_file = format!("{}/{HTML_REPORT_FILE}", settings.output_folder);
+    match fs::write(&report_file, report) {
+        Ok(()) => println!("Saved html report to {report_file}"),
+        Err(e) => eprintln!("Failed to save html report to {report_file} - {e}"),
+    }
+}
+
+// Missing files are silently skipped, e.g. diff image may be disabled in settings
+fn embedded_image(path: &str, mime_type: &str) -> String {
+    match fs::read(path) {
+        Ok(content) => format!(r#"<img src="data:{mime_type};base64,{}">"#, STANDARD.encode(content)),
+        Err(_) => String::new(),
+    }
+}
+
+fn image_figure(caption: &str, content: &str) -> String {
+    format!("<figure>{content}<figcaption>{}</figcaption></figure>\n", escape_html(caption))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+const REPORT_HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>SVG tester report</title>
+<style>
+body { font-family: sans-serif; margin: 20px; }
+.controls { position: sticky; top: 0; background: white; padding: 8px 0; border-bottom: 1px solid #ccc; }
+.controls label { margin-right: 16px; }
+.file { border-bottom: 1px solid #ccc; padding: 8px 0; }
+.images { display: flex; flex-wrap: wrap; gap: 12px; align-items: flex-start; }
+figure { margin: 0; max-width: 420px; }
+figure img { max-width: 400px; max-height: 400px; border: 1px solid #ddd; background: repeating-conic-gradient(#eee 0% 25%, white 0% 50%) 50% / 16px 16px; }
+figcaption { font-weight: bold; }
+pre { max-height: 200px; overflow: auto; background: #f4f4f4; font-size: 11px; white-space: pre-wrap; }
+</style>
+</head>
+<body>
+<h1>SVG tester report</h1>
+"#;
+
+const REPORT_SORT_CONTROLS: &str = r#"<label>Sort by <select id="sort">
+<option value="name">file name</option>
+<option value="hash">hash difference</option>
+<option value="pixels">differing pixels</option>
+<option value="ssim">ssim</option>
+</select></label>
+"#;
+
+const REPORT_FOOTER: &str = r#"<script>
+const files = document.getElementById("files");
+const toolSelect = document.getElementById("tool");
+const outcomeSelect = document.getElementById("outcome");
+const sortSelect = document.getElementById("sort");
+
+function update() {
+    const tool = toolSelect.value;
+    const outcome = outcomeSelect.value;
+    for (const file of files.children) {
+        const visible = (tool === "" || file.dataset.tools.includes(" " + tool + " "))
+            && (outcome === "" || file.dataset.outcomes.includes(" " + outcome + " "));
+        file.style.display = visible ? "" : "none";
+    }
+}
+
+function sort() {
+    const key = sortSelect.value;
+    const items = Array.from(files.children);
+    items.sort((a, b) => {
+        if (key === "name") {
+            return a.dataset.name.localeCompare(b.dataset.name);
+        }
+        // Most different files are shown first
+        if (key === "ssim") {
+            return parseFloat(a.dataset.ssim) - parseFloat(b.dataset.ssim);
+        }
+        return parseFloat(b.dataset[key]) - parseFloat(a.dataset[key]);
+    });
+    for (const item of items) {
+        files.appendChild(item);
+    }
+}
+
+toolSelect.addEventListener("change", update);
+outcomeSelect.addEventListener("change", update);
+sortSelect.addEventListener("change", sort);
+sort();
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,89 @@
+use crate::features::SvgFeatures;
+use crate::setting::{IgnoreRule, Settings};
+
+// Rule which matched file, with description of what was found
+pub struct IgnoreMatch {
+    pub rule: String,
+    pub reason: String,
+}
+
+impl IgnoreRule {
+    fn applies_to(&self, mode: &str, tool: &str) -> bool {
+        (self.modes.is_empty() || self.modes.iter().any(|e| e == mode)) && (self.tools.is_empty() || self.tools.iter().any(|e| e == tool))
+    }
+
+    fn matched(&self, found: &str) -> IgnoreMatch {
+        let issue = if self.issue.is_empty() {
+            String::new()
+        } else {
+            format!(" - {}", self.issue)
+        };
+        IgnoreMatch {
+            rule: self.name.clone(),
+            reason: format!("{found} (rule `{}`{issue})", self.name),
+        }
+    }
+}
+
+// Checked before rendering, so file that cannot be rendered properly by tool is not rendered by it at all
+pub fn find_input_ignore_rule(settings: &Settings, mode: &str, tool: &str, features: &SvgFeatures) -> Option<IgnoreMatch> {
+    settings
+        .ignore_rules
+        .iter()
+        .filter(|e| !e.input_features_any.is_empty() && e.applies_to(mode, tool))
+        .find_map(|rule| {
+            rule.input_features_any
+                .iter()
+                .find(|feature| features.count(feature) >= rule.input_min_count)
+                .map(|feature| match rule.input_min_count {
+                    1 => rule.matched(&format!("uses {feature}")),
+                    count => rule.matched(&format!("uses {feature} at least {count} times")),
+                })
+        })
+}
+
+// Checked when tool failed, to suppress known crashes and errors
+pub fn find_output_ignore_rule(settings: &Settings, mode: &str, tool: &str, output: &str) -> Option<IgnoreMatch> {
+    settings
+        .ignore_rules
+        .iter()
+        .filter(|e| !e.output_contains_all.is_empty() && e.applies_to(mode, tool))
+        .find(|rule| rule.output_contains_all.iter().all(|item| output.contains(item.as_str())))
+        .map(|rule| rule.matched(&format!("{tool} output contains {}", rule.output_contains_all.join(", "))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::try_load_settings;
+
+    fn matched_rule(settings: &Settings, tool: &str, content: &str) -> Option<String> {
+        find_input_ignore_rule(settings, "compare", tool, &SvgFeatures::from_content(content)).map(|e| e.rule)
+    }
+
+    fn shipped_settings() -> Settings {
+        let Ok(settings) = try_load_settings(concat!(env!("CARGO_MANIFEST_DIR"), "/settings.toml"), &[]) else {
+            panic!("settings.toml is not valid");
+        };
+        settings
+    }
+
+    #[test]
+    fn rule_from_settings_matches_unusual_xlink_prefix() {
+        let settings = shipped_settings();
+        let unusual_prefix = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:x="http://www.w3.org/1999/xlink"><use x:href="#a"/></svg>"##;
+        let usual_prefix = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><use xlink:href="#a"/></svg>"##;
+        assert_eq!(
+            matched_rule(&settings, "thorvg", unusual_prefix).as_deref(),
+            Some("thorvg-not-supported-elements")
+        );
+        assert_eq!(matched_rule(&settings, "thorvg", usual_prefix), None);
+    }
+
+    #[test]
+    fn rule_limited_to_tool_not_matches_other_tools() {
+        let settings = shipped_settings();
+        let content = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:x="http://www.w3.org/1999/xlink"><use x:href="#a"/></svg>"##;
+        assert_eq!(matched_rule(&settings, "resvg", content), None);
+    }
+}
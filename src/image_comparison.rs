@@ -1,13 +1,13 @@
-use std::cmp::{max, min};
 use std::fs;
 use std::path::Path;
-use std::sync::atomic::{AtomicU32, Ordering};
 
 use bk_tree::BKTree;
 use image::{DynamicImage, GenericImage, GenericImageView};
 use image_hasher::{HashAlg, HasherConfig};
 
-use crate::common::save_problematic_file;
+use crate::common::mirrored_output_folder;
+use crate::metrics::{get_pixel_metrics, get_ssim, ImageMetrics};
+use crate::outcome::Outcome;
 use crate::setting::Settings;
 use crate::Hamming;
 
@@ -35,79 +35,63 @@ pub fn remove_alpha_channel(dynamic_image: &mut DynamicImage) {
     // dynamic_image.save(path).unwrap();
 }
 
-pub fn compare_images(
-    source_file: &str,
-    first_output_png: &str,
-    other_output_png: &str,
-    settings: &Settings,
-    broken_items: &AtomicU32,
-    problematic_items: &AtomicU32,
-) {
-    let mut first_image = match image::open(first_output_png) {
-        Ok(t) => t,
-        Err(e) => {
-            save_problematic_file(
-                &settings.problematic_files_path, &settings.first_tool_name, source_file, settings.remove_problematic_files_after_copying,
-            );
-            println!("Failed to open {first_output_png}, reason {e} (Probably app failed to generate png file)");
-            problematic_items.fetch_add(1, Ordering::Relaxed);
-            return;
+// Alpha channel is removed already here, because each image is compared with multiple other images
+pub fn load_image(output_png: &str) -> Result<DynamicImage, Outcome> {
+    match image::open(output_png) {
+        Ok(t) => {
+            // Pixel metrics expects same pixel format in both images
+            let mut t = DynamicImage::ImageRgba8(t.to_rgba8());
+            remove_alpha_channel(&mut t);
+            Ok(t)
         }
-    };
-    let mut second_image = match image::open(other_output_png) {
-        Ok(t) => t,
-        Err(e) => {
-            save_problematic_file(
-                &settings.problematic_files_path, &settings.other_tool_name, source_file, settings.remove_problematic_files_after_copying,
-            );
-            println!("Failed to open {other_output_png}, reason {e}");
-            problematic_items.fetch_add(1, Ordering::Relaxed);
-            return;
-        }
-    };
+        Err(e) => Err(Outcome::UndecodablePng { reason: e.to_string() }),
+    }
+}
 
+pub fn compare_images(first_image: &DynamicImage, second_image: &DynamicImage, settings: &Settings) -> (Outcome, Option<ImageMetrics>) {
     if second_image.width() != first_image.width() || second_image.height() != first_image.height() {
-        save_problematic_file(
-            &settings.problematic_files_path, &settings.first_tool_name, source_file, settings.remove_problematic_files_after_copying,
-        );
-        println!(
-            "Ignored images with non equal lengths {} {}x{}, {} {}x{} - diff {}x{}",
-            other_output_png,
-            second_image.width(),
-            second_image.height(),
-            first_output_png,
-            first_image.width(),
-            first_image.height(),
-            max(second_image.width(), first_image.width()) - min(second_image.width(), first_image.width()),
-            max(second_image.height(), first_image.height()) - min(second_image.height(), first_image.height()),
-        );
-        problematic_items.fetch_add(1, Ordering::Relaxed);
-        return;
+        let outcome = Outcome::SizeMismatch {
+            first: (first_image.width(), first_image.height()),
+            other: (second_image.width(), second_image.height()),
+        };
+        return (outcome, None);
     }
 
-    let difference_between = get_difference_between_images(&[HashAlg::Median, HashAlg::Mean], &mut first_image, &mut second_image, true);
+    let metrics = get_image_metrics(first_image, second_image, settings.pixel_tolerance);
+    if metrics.is_broken(settings) {
+        let exceeded = metrics.exceeded_metrics(settings);
+        return (Outcome::VisualDifference { exceeded }, Some(metrics));
+    }
+    (Outcome::Success, Some(metrics))
+}
 
-    if difference_between.iter().any(|e| e <= &settings.max_difference) {
-    } else {
-        copy_to_file_name(first_output_png, &settings.output_folder);
-        copy_to_file_name(other_output_png, &settings.output_folder);
-        copy_to_file_name(source_file, &settings.output_folder);
-        broken_items.fetch_add(1, Ordering::Relaxed);
-        if settings.remove_broken_files_after_copying {
-            fs::remove_file(source_file).unwrap();
-        }
+// Images must be loaded with `load_image`
+pub fn get_image_metrics(first_image: &DynamicImage, second_image: &DynamicImage, pixel_tolerance: u8) -> ImageMetrics {
+    let difference_between = get_difference_between_images(&[HashAlg::Median, HashAlg::Mean], first_image, second_image, false);
+    let hash_difference = difference_between.iter().copied().min().unwrap_or_default();
+
+    let first_rgba = first_image.as_rgba8().expect("Image should be converted to rgba8 when loading");
+    let second_rgba = second_image.as_rgba8().expect("Image should be converted to rgba8 when loading");
+    let (differing_pixels, max_channel_delta, rmse, psnr) = get_pixel_metrics(first_rgba, second_rgba, pixel_tolerance);
+    let ssim = get_ssim(first_rgba, second_rgba);
+
+    ImageMetrics {
+        hash_difference,
+        differing_pixels,
+        max_channel_delta,
+        rmse,
+        psnr,
+        ssim,
     }
 }
 
-pub fn get_difference_between_images(
-    hash_algs: &[HashAlg],
-    first_image: &mut DynamicImage,
-    second_image: &mut DynamicImage,
-    remove_alpha: bool,
-) -> Vec<u32> {
+pub fn get_difference_between_images(hash_algs: &[HashAlg], first_image: &DynamicImage, second_image: &DynamicImage, remove_alpha: bool) -> Vec<u32> {
     if remove_alpha {
-        remove_alpha_channel(first_image);
-        remove_alpha_channel(second_image);
+        let mut first_image = first_image.clone();
+        let mut second_image = second_image.clone();
+        remove_alpha_channel(&mut first_image);
+        remove_alpha_channel(&mut second_image);
+        return get_difference_between_images(hash_algs, &first_image, &second_image, false);
     }
     let mut differences = vec![];
     for hash_alg in hash_algs {
@@ -129,6 +113,11 @@ pub fn get_difference_between_images(
     differences
 }
 
+// Results of each pair of tools are stored in separate folder, with same subfolders as source file
+pub fn pair_output_folder(settings: &Settings, first_tool: &str, other_tool: &str, source_file: &str) -> String {
+    mirrored_output_folder(settings, &format!("{}/{first_tool}_vs_{other_tool}", settings.output_folder), source_file)
+}
+
 pub fn copy_to_file_name(original_file: &str, output_folder: &str) {
     fs::copy(
         original_file,
@@ -136,3 +125,57 @@ pub fn copy_to_file_name(original_file: &str, output_folder: &str) {
     )
     .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::metrics::Metric;
+
+    fn image(pixel: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 16, Rgba(pixel)))
+    }
+
+    #[test]
+    fn identical_images_are_same() {
+        let (outcome, metrics) = compare_images(&image([10, 20, 30, 255]), &image([10, 20, 30, 255]), &Settings::default());
+        assert_eq!(outcome, Outcome::Success);
+        let Some(metrics) = metrics else {
+            panic!("Metrics should be calculated for images with same size");
+        };
+        assert_eq!((metrics.hash_difference, metrics.differing_pixels, metrics.max_channel_delta), (0, 0, 0));
+        assert_eq!(metrics.psnr, f64::INFINITY);
+        assert!((metrics.ssim - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reports_metrics_exceeded_by_different_images() {
+        let settings = Settings {
+            broken_metrics: vec![Metric::MaxChannelDelta, Metric::Psnr],
+            max_channel_delta: 50,
+            ..Settings::default()
+        };
+        let (outcome, _) = compare_images(&image([0, 0, 0, 255]), &image([40, 40, 40, 255]), &settings);
+        assert_eq!(
+            outcome,
+            Outcome::VisualDifference {
+                exceeded: vec![Metric::Psnr]
+            }
+        );
+    }
+
+    #[test]
+    fn reports_images_with_different_sizes() {
+        let other_image = DynamicImage::ImageRgba8(RgbaImage::new(16, 8));
+        let (outcome, metrics) = compare_images(&image([0, 0, 0, 255]), &other_image, &Settings::default());
+        assert_eq!(
+            outcome,
+            Outcome::SizeMismatch {
+                first: (16, 16),
+                other: (16, 8)
+            }
+        );
+        assert!(metrics.is_none());
+    }
+}
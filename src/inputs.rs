@@ -0,0 +1,249 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+use flate2::read::GzDecoder;
+use walkdir::WalkDir;
+
+use crate::scratch::scratch_folder;
+use crate::setting::Settings;
+
+const ZIP_EXTENSIONS: [&str; 1] = [".zip"];
+const TAR_EXTENSIONS: [&str; 1] = [".tar"];
+const TAR_GZ_EXTENSIONS: [&str; 2] = [".tar.gz", ".tgz"];
+const SVGZ_EXTENSION: &str = ".svgz";
+
+// Place where files are really stored, archives and .svgz files are extracted to scratch folder
+// `name` is added before relative path of file, to not mix files from different inputs
+pub struct InputFolder {
+    pub folder: PathBuf,
+    pub name: Option<String>,
+    // Archive or folder from which files were extracted, None when files are checked in place
+    pub source: Option<String>,
+    // Files are decompressed `.svgz` files, saved with `.svg` added to their names
+    pub svgz: bool,
+}
+
+// Extracts archives and .svgz files from `folder_with_files_to_check`, input files are never modified
+// Extracted files are removed when app exits
+pub fn prepare_input_folders(settings: &mut Settings) {
+    let names = input_names(&settings.folder_with_files_to_check);
+    let mut input_folders = Vec::new();
+    for (index, (input, name)) in settings.folder_with_files_to_check.iter().zip(names).enumerate() {
+        let path = Path::new(input);
+        let svgz_folder = scratch_folder().join("inputs").join(format!("{index}_svgz"));
+        if path.is_file() && is_svgz(path) {
+            input_folders.push(decompress_single_svgz_file(path, &svgz_folder, name));
+            continue;
+        }
+        let is_archive = path.is_file() && is_archive(input);
+
+        let folder = if is_archive {
+            let archive_folder = scratch_folder().join("inputs").join(format!("{index}_archive"));
+            println!("Extracting archive {input} to {}", archive_folder.display());
+            if let Err(e) = extract_archive(input, &archive_folder) {
+                eprintln!("Failed to extract archive {input}, reason {e}");
+                process::exit(1);
+            }
+            canonical(archive_folder)
+        } else {
+            canonical(path.to_path_buf())
+        };
+        let has_svgz_files = folder.is_dir() && decompress_svgz_files(&folder, &svgz_folder, settings.recursive_search);
+
+        input_folders.push(InputFolder {
+            folder,
+            name: name.clone(),
+            source: is_archive.then(|| input.clone()),
+            svgz: false,
+        });
+        if has_svgz_files {
+            input_folders.push(InputFolder {
+                folder: canonical(svgz_folder),
+                name,
+                source: Some(input.clone()),
+                svgz: true,
+            });
+        }
+    }
+    settings.input_folders = input_folders;
+}
+
+// Names added before relative paths of files, when more than one input is checked
+// Usually it is name of folder or archive, but when it is the same for several inputs e.g. `a/svgs` and `b/svgs`, position of input is added e.g. `svgs_2`
+pub fn input_names(inputs: &[String]) -> Vec<Option<String>> {
+    if inputs.len() < 2 {
+        return vec![None; inputs.len()];
+    }
+    let base_names = inputs
+        .iter()
+        .map(|input| {
+            Path::new(input)
+                .file_name()
+                .map_or_else(|| input.clone(), |e| e.to_string_lossy().to_string())
+        })
+        .collect::<Vec<_>>();
+    base_names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if base_names.iter().filter(|e| *e == name).count() > 1 {
+                Some(format!("{name}_{}", index + 1))
+            } else {
+                Some(name.clone())
+            }
+        })
+        .collect()
+}
+
+// Files found in folders are canonicalized, so also folder must be, to be able to find relative paths
+fn canonical(folder: PathBuf) -> PathBuf {
+    folder.canonicalize().unwrap_or(folder)
+}
+
+fn is_svgz(path: &Path) -> bool {
+    path.to_string_lossy().to_lowercase().ends_with(SVGZ_EXTENSION)
+}
+
+fn is_archive(path: &str) -> bool {
+    ZIP_EXTENSIONS
+        .iter()
+        .chain(&TAR_EXTENSIONS)
+        .chain(&TAR_GZ_EXTENSIONS)
+        .any(|e| path.to_lowercase().ends_with(e))
+}
+
+fn extract_archive(archive_path: &str, output_folder: &Path) -> Result<(), String> {
+    fs::create_dir_all(output_folder).map_err(|e| e.to_string())?;
+    let file = File::open(archive_path).map_err(|e| e.to_string())?;
+    let lowercase_path = archive_path.to_lowercase();
+    // Both extractors skip members with absolute paths or `..`, so files cannot be written outside output folder
+    if ZIP_EXTENSIONS.iter().any(|e| lowercase_path.ends_with(e)) {
+        zip::ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(output_folder))
+            .map_err(|e| e.to_string())
+    } else if TAR_GZ_EXTENSIONS.iter().any(|e| lowercase_path.ends_with(e)) {
+        tar::Archive::new(GzDecoder::new(file)).unpack(output_folder).map_err(|e| e.to_string())
+    } else {
+        tar::Archive::new(file).unpack(output_folder).map_err(|e| e.to_string())
+    }
+}
+
+// Input which is single `.svgz` file is checked as folder with only this file, it points to original file in results
+fn decompress_single_svgz_file(path: &Path, output_folder: &Path, name: Option<String>) -> InputFolder {
+    let file_name = path.file_name().unwrap_or_default();
+    if let Err(e) = decompress_svgz_file(path, &svgz_output_path(output_folder, Path::new(file_name))) {
+        eprintln!("Failed to decompress {}, reason {e}", path.display());
+        process::exit(1);
+    }
+    let parent_folder = canonical(path.to_path_buf()).parent().map(Path::to_path_buf).unwrap_or_default();
+    InputFolder {
+        folder: canonical(output_folder.to_path_buf()),
+        name,
+        source: Some(parent_folder.to_string_lossy().to_string()),
+        svgz: true,
+    }
+}
+
+// Decompressed `a.svgz` is saved as `a.svgz.svg` in the same relative place in output folder, so it not gets the same relative name as `a.svg`
+// Returns true if any file was decompressed
+fn decompress_svgz_files(input_folder: &Path, output_folder: &Path, recursive_search: bool) -> bool {
+    let max_depth = if recursive_search { usize::MAX } else { 1 };
+    let mut decompressed_any = false;
+    for entry in WalkDir::new(input_folder).max_depth(max_depth).into_iter().flatten() {
+        let path = entry.path();
+        if !path.is_file() || !is_svgz(path) {
+            continue;
+        }
+        let relative_path = path.strip_prefix(input_folder).unwrap_or(path);
+        match decompress_svgz_file(path, &svgz_output_path(output_folder, relative_path)) {
+            Ok(()) => decompressed_any = true,
+            Err(e) => eprintln!("Failed to decompress {}, reason {e}", path.display()),
+        }
+    }
+    decompressed_any
+}
+
+fn svgz_output_path(output_folder: &Path, relative_path: &Path) -> PathBuf {
+    let mut output_path = output_folder.join(relative_path).into_os_string();
+    output_path.push(".svg");
+    PathBuf::from(output_path)
+}
+
+fn decompress_svgz_file(path: &Path, output_path: &Path) -> io::Result<()> {
+    let mut content = Vec::new();
+    GzDecoder::new(File::open(path)?).read_to_end(&mut content)?;
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(output_path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::common::{original_file_name, relative_file_name};
+
+    fn write_svgz(path: &Path, content: &str) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    // Relative names of all svg files found in prepared inputs, with their original names
+    fn prepared_files(input: &Path) -> Vec<(String, Option<String>)> {
+        let mut settings = Settings {
+            folder_with_files_to_check: vec![input.to_string_lossy().to_string()],
+            ..Settings::default()
+        };
+        prepare_input_folders(&mut settings);
+        let mut files = settings
+            .input_folders
+            .iter()
+            .flat_map(|input| WalkDir::new(&input.folder).into_iter().flatten())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let file = e.path().to_string_lossy().to_string();
+                (relative_file_name(&settings, &file), original_file_name(&settings, &file))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn decompresses_single_svgz_input() {
+        let folder = scratch_folder().join("single_svgz_input");
+        fs::create_dir_all(&folder).unwrap();
+        let folder = canonical(folder);
+        write_svgz(&folder.join("a.svgz"), "<svg/>");
+
+        let files = prepared_files(&folder.join("a.svgz"));
+        assert_eq!(files, [("a.svgz.svg".to_string(), Some(format!("{}/a.svgz", folder.display())))]);
+    }
+
+    #[test]
+    fn keeps_svgz_files_apart_from_svg_files_with_same_name() {
+        let folder = scratch_folder().join("svgz_and_svg_input");
+        fs::create_dir_all(&folder).unwrap();
+        let folder = canonical(folder);
+        fs::write(folder.join("a.svg"), "<svg/>").unwrap();
+        write_svgz(&folder.join("a.svgz"), "<svg/>");
+
+        let files = prepared_files(&folder);
+        assert_eq!(
+            files,
+            [
+                ("a.svg".to_string(), None),
+                ("a.svgz".to_string(), None),
+                ("a.svgz.svg".to_string(), Some(format!("{}/a.svgz", folder.display())))
+            ]
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// After SIGINT or SIGTERM(e.g. sent by `timeout` in CI), files not started yet are not checked, so reports can be saved with
+// results of already checked files
+// Second SIGINT kills app immediately, SIGTERM not, because `timeout` sends it twice - to app and to its process group
+pub fn stop_checking_on_interrupt() {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: handler only stores atomic value and restores default handler, which are async-signal-safe
+        unsafe {
+            libc::signal(signal, handle_interrupt as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+extern "C" fn handle_interrupt(signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+    if signal == libc::SIGINT {
+        // SAFETY: signal is async-signal-safe
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+        }
+    }
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+// Printed before saving reports, so it is visible that they not contain all files
+pub fn print_interrupted(checked_files: usize, all_files: usize) {
+    if is_interrupted() {
+        eprintln!("Interrupted - checked only {checked_files}/{all_files} files, reports contain only them");
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::outcome::{Category, FileResult, Summary};
+use crate::runner::{run_command, ProcessLimits};
+use crate::setting::Settings;
+
+pub const JSON_RESULTS_FILE: &str = "results.json";
+
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct JsonResults<'a> {
+    metadata: RunMetadata<'a>,
+    summary: JsonSummary<'a>,
+    files: Vec<JsonFileResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct RunMetadata<'a> {
+    app_version: &'static str,
+    mode: &'a str,
+    // Unix timestamp in seconds, when results were saved
+    timestamp: u64,
+    settings: &'a Settings,
+    // First line printed by tool with its version arguments, null when tool could not be run
+    tool_versions: BTreeMap<&'a str, Option<String>>,
+}
+
+#[derive(Serialize)]
+struct JsonSummary<'a> {
+    files: usize,
+    categories: &'a BTreeMap<Category, usize>,
+}
+
+#[derive(Serialize)]
+struct JsonFileResult<'a> {
+    #[serde(flatten)]
+    result: &'a FileResult,
+    category: Category,
+}
+
+// `tools` contains name, path and version arguments of each used tool
+pub fn save_json_results(
+    settings: &Settings,
+    output_folder: &str,
+    mode: &str,
+    tools: &[(&str, &str, &str)],
+    summary: &Summary,
+    results: &[FileResult],
+) {
+    let tool_versions = tools
+        .iter()
+        .map(|(name, path, version_arguments)| (*name, get_tool_version(path, version_arguments)))
+        .collect();
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|e| e.as_secs()).unwrap_or_default();
+
+    let json_results = JsonResults {
+        metadata: RunMetadata {
+            app_version: env!("CARGO_PKG_VERSION"),
+            mode,
+            timestamp,
+            settings,
+            tool_versions,
+        },
+        summary: JsonSummary {
+            files: summary.files,
+            categories: &summary.categories,
+        },
+        files: results
+            .iter()
+            .map(|result| JsonFileResult {
+                result,
+                category: result.category(),
+            })
+            .collect(),
+    };
+
+    let results_file = format!("{output_folder}/{JSON_RESULTS_FILE}");
+    let content = match serde_json::to_string_pretty(&json_results) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to serialize results - {e}");
+            return;
+        }
+    };
+    let _ = fs::create_dir_all(output_folder);
+    match fs::write(&results_file, content) {
+        Ok(()) => println!("Saved results to {results_file}"),
+        Err(e) => eprintln!("Failed to save results to {results_file} - {e}"),
+    }
+}
+
+fn get_tool_version(path: &str, version_arguments: &str) -> Option<String> {
+    let limits = ProcessLimits {
+        timeout: VERSION_TIMEOUT,
+        memory_limit: 0,
+        cpu_time_limit: 0,
+    };
+    let output = run_command(Command::new(path).args(version_arguments.split(' ')), &limits).ok()?;
+    output
+        .all_output()
+        .lines()
+        .map(str::trim)
+        .find(|e| !e.is_empty())
+        .map(ToString::to_string)
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+
+use crate::outcome::{Category, FileResult, Outcome, ToolResult};
+
+pub const JUNIT_REPORT_FILE: &str = "junit.xml";
+
+enum TestCaseResult {
+    Passed,
+    // Images looks different
+    Failure { kind: &'static str, message: String, details: String },
+    // Tool failed, so result could not be checked
+    Error { kind: &'static str, message: String, details: String },
+    Skipped { message: String },
+}
+
+struct TestCase {
+    name: String,
+    // Duration of all tools used by this test case, in seconds
+    time: f64,
+    result: TestCaseResult,
+}
+
+// Every pair of tools(or tool and reference images) is separate test suite, with one test case per file
+pub fn save_junit_report_for_pairs(output_folder: &str, name: &str, pairs: &[(String, String)], results: &[FileResult]) {
+    let mut test_suites: BTreeMap<String, Vec<TestCase>> = BTreeMap::new();
+    for (first_tool, other_tool) in pairs {
+        let test_cases = test_suites.entry(format!("{first_tool} vs {other_tool}")).or_default();
+        for file_result in results {
+            let tools = file_result
+                .tools
+                .iter()
+                .filter(|e| e.tool == *first_tool || e.tool == *other_tool)
+                .collect::<Vec<_>>();
+            let pair_result = file_result
+                .pairs
+                .iter()
+                .find(|e| e.first_tool == *first_tool && e.other_tool == *other_tool);
+
+            let result = if let Some(ignore_reason) = &file_result.ignore_reason {
+                TestCaseResult::Skipped {
+                    message: ignore_reason.clone(),
+                }
+            } else if let Some(tool_result) = tools.iter().find(|e| e.outcome.category() == Category::Ignored) {
+                TestCaseResult::Skipped {
+                    message: tool_result.outcome.to_string(),
+                }
+            } else if let Some(tool_result) = tools.iter().find(|e| !e.outcome.is_success()) {
+                tool_error(tool_result)
+            } else if let Some(pair_result) = pair_result {
+                outcome_result(
+                    &pair_result.outcome,
+                    pair_result.metrics.as_ref().map(ToString::to_string).unwrap_or_default(),
+                )
+            } else {
+                TestCaseResult::Passed
+            };
+            test_cases.push(TestCase {
+                name: file_result.name().to_string(),
+                time: tools_time(&tools),
+                result,
+            });
+        }
+    }
+    save_junit_report(output_folder, name, &test_suites);
+}
+
+// In thorvg and lottie modes, each file is checked only by one tool
+pub fn save_junit_report_for_tool(output_folder: &str, tool_name: &str, results: &[FileResult]) {
+    let test_cases = results
+        .iter()
+        .map(|file_result| {
+            let tools = file_result.tools.iter().collect::<Vec<_>>();
+            let result = if let Some(ignore_reason) = &file_result.ignore_reason {
+                TestCaseResult::Skipped {
+                    message: ignore_reason.clone(),
+                }
+            } else if let Some(tool_result) = tools.iter().find(|e| !e.outcome.is_success()) {
+                tool_error(tool_result)
+            } else {
+                TestCaseResult::Passed
+            };
+            TestCase {
+                name: file_result.name().to_string(),
+                time: tools_time(&tools),
+                result,
+            }
+        })
+        .collect();
+    let test_suites = BTreeMap::from([(tool_name.to_string(), test_cases)]);
+    save_junit_report(output_folder, tool_name, &test_suites);
+}
+
+fn tool_error(tool_result: &ToolResult) -> TestCaseResult {
+    TestCaseResult::Error {
+        kind: tool_result.outcome.kind(),
+        message: format!("{} - {}", tool_result.tool, tool_result.outcome),
+        details: tool_result.output.clone(),
+    }
+}
+
+fn outcome_result(outcome: &Outcome, details: String) -> TestCaseResult {
+    match outcome.category() {
+        Category::Passed | Category::Ignored => TestCaseResult::Passed,
+        Category::Broken => TestCaseResult::Failure {
+            kind: outcome.kind(),
+            message: outcome.to_string(),
+            details,
+        },
+        Category::Problematic => TestCaseResult::Error {
+            kind: outcome.kind(),
+            message: outcome.to_string(),
+            details,
+        },
+    }
+}
+
+fn tools_time(tools: &[&ToolResult]) -> f64 {
+    tools.iter().filter_map(|e| e.duration).fold(0.0, |sum, e| sum + e.as_secs_f64())
+}
+
+fn save_junit_report(output_folder: &str, name: &str, test_suites: &BTreeMap<String, Vec<TestCase>>) {
+    let count = |test_cases: &[TestCase], check: fn(&TestCaseResult) -> bool| test_cases.iter().filter(|e| check(&e.result)).count();
+    let is_failure = |result: &TestCaseResult| matches!(result, TestCaseResult::Failure { .. });
+    let is_error = |result: &TestCaseResult| matches!(result, TestCaseResult::Error { .. });
+    let is_skipped = |result: &TestCaseResult| matches!(result, TestCaseResult::Skipped { .. });
+
+    let all_test_cases = test_suites.values().flatten().collect::<Vec<_>>();
+    let mut report = String::new();
+    report.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        report,
+        r#"<testsuites name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}">"#,
+        escape_xml(name),
+        all_test_cases.len(),
+        all_test_cases.iter().filter(|e| is_failure(&e.result)).count(),
+        all_test_cases.iter().filter(|e| is_error(&e.result)).count(),
+        all_test_cases.iter().filter(|e| is_skipped(&e.result)).count(),
+        all_test_cases.iter().fold(0.0, |sum, e| sum + e.time)
+    );
+    for (suite_name, test_cases) in test_suites {
+        let _ = writeln!(
+            report,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}">"#,
+            escape_xml(suite_name),
+            test_cases.len(),
+            count(test_cases, is_failure),
+            count(test_cases, is_error),
+            count(test_cases, is_skipped),
+            test_cases.iter().fold(0.0, |sum, e| sum + e.time)
+        );
+        for test_case in test_cases {
+            let _ = write!(
+                report,
+                r#"    <testcase classname="{}" name="{}" time="{:.3}""#,
+                escape_xml(suite_name),
+                escape_xml(&test_case.name),
+                test_case.time
+            );
+            match &test_case.result {
+                TestCaseResult::Passed => report.push_str("/>\n"),
+                TestCaseResult::Failure { kind, message, details } | TestCaseResult::Error { kind, message, details } => {
+                    let element = if is_failure(&test_case.result) { "failure" } else { "error" };
+                    let _ = writeln!(
+                        report,
+                        ">\n      <{element} type=\"{kind}\" message=\"{}\">{}</{element}>\n    </testcase>",
+                        escape_xml(message),
+                        escape_xml(details)
+                    );
+                }
+                TestCaseResult::Skipped { message } => {
+                    let _ = writeln!(report, ">\n      <skipped message=\"{}\"/>\n    </testcase>", escape_xml(message));
+                }
+            }
+        }
+        report.push_str("  </testsuite>\n");
+    }
+    report.push_str("</testsuites>\n");
+
+    let report_file = format!("{output_folder}/{JUNIT_REPORT_FILE}");
+    let _ = fs::create_dir_all(output_folder);
+    match fs::write(&report_file, report) {
+        Ok(()) => println!("Saved junit report to {report_file}"),
+        Err(e) => eprintln!("Failed to save junit report to {report_file} - {e}"),
+    }
+}
+
+// Tool output may contain control characters, which are not allowed in xml
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(character),
+            _ if character.is_control() => escaped.push('\u{FFFD}'),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn keeps_whitespace_and_unicode() {
+        assert_eq!(escape_xml("line 1\n\tline 2\r\nżółw"), "line 1\n\tline 2\r\nżółw");
+    }
+
+    #[test]
+    fn replaces_control_characters() {
+        assert_eq!(escape_xml("\u{1b}[31mred\u{1b}[0m\0"), "\u{FFFD}[31mred\u{FFFD}[0m\u{FFFD}");
+    }
+}
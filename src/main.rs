@@ -3,22 +3,54 @@
 use std::collections::BTreeMap;
 use std::path::Path;
 use std::process::exit;
-use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
 use std::{fs, process};
 
+use clap::Parser;
 use image_hasher::HashAlg;
-use rayon::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use walkdir::WalkDir;
 
-use crate::image_comparison::{compare_images, get_difference_between_images};
+use crate::bisect::{bisect_files, BisectOptions};
+use crate::cli::{Cli, Mode};
+use crate::common::{absolute_path, relative_file_name};
+use crate::compare::compare_svg_files;
+use crate::golden::test_golden;
+use crate::image_comparison::get_difference_between_images;
+use crate::inputs::prepare_input_folders;
 use crate::lottie::test_lottie;
-use crate::setting::{load_settings, Settings};
-use crate::svg_2_png::convert_svg_to_png;
+use crate::reduce::{reduce_file, ReduceOptions};
+use crate::render_cache::prune_render_cache;
+use crate::setting::{build_glob_set, load_settings, parse_shard, Settings};
 use crate::thorvg::test_thorvg;
 
+mod baseline;
+mod bisect;
+mod cli;
 mod common;
+mod compare;
+mod crash_mode;
+mod diff_image;
+mod feature_statistics;
+mod features;
+mod fingerprint;
+mod golden;
+mod html_report;
+mod ignore_rules;
 mod image_comparison;
+mod inputs;
+mod interrupt;
+mod json_report;
+mod junit_report;
 mod lottie;
+mod metrics;
+mod outcome;
+mod reduce;
+mod render_cache;
+mod results_log;
+mod runner;
+mod sanitizer;
+mod scratch;
 mod setting;
 mod svg_2_png;
 mod thorvg;
@@ -37,72 +69,92 @@ impl bk_tree::Metric<Vec<u8>> for Hamming {
 
 fn find_files(settings: &Settings, extension: &str) -> Vec<String> {
     let mut files_to_check = Vec::new();
-    println!(
-        "Starting to collect files to check - extension {extension} in {}",
-        settings.folder_with_files_to_check
-    );
-    if Path::new(&settings.folder_with_files_to_check).is_dir() {
-        for entry in WalkDir::new(&settings.folder_with_files_to_check).max_depth(1).into_iter().flatten() {
-            let path = entry.path();
-            if !path.is_file() {
-                continue;
+    // Patterns are validated when loading settings
+    let include_patterns = build_glob_set(&settings.include_patterns).unwrap();
+    let exclude_patterns = build_glob_set(&settings.exclude_patterns).unwrap();
+    let is_selected = |relative_path: &Path| {
+        relative_path.to_string_lossy().ends_with(extension)
+            && (settings.include_patterns.is_empty() || include_patterns.is_match(relative_path))
+            && !exclude_patterns.is_match(relative_path)
+    };
+    let max_depth = if settings.recursive_search { usize::MAX } else { 1 };
+
+    for input in &settings.input_folders {
+        let folder = &input.folder;
+        println!("Starting to collect files to check - extension {extension} in {}", folder.display());
+        if folder.is_dir() {
+            for entry in WalkDir::new(folder).max_depth(max_depth).into_iter().flatten() {
+                let path = entry.path();
+                if !path.is_file() || !is_selected(path.strip_prefix(folder).unwrap_or(path)) {
+                    continue;
+                }
+                if let Ok(full_path) = path.canonicalize() {
+                    files_to_check.push(full_path.to_string_lossy().to_string());
+                }
             }
-            let full_path = match path.canonicalize() {
-                Ok(t) => t.to_string_lossy().to_string(),
-                Err(_) => continue,
-            };
-            if full_path.ends_with(extension) {
-                files_to_check.push(full_path);
+        } else {
+            match fs::read_to_string(folder) {
+                Ok(t) => files_to_check.extend(
+                    t.split('\n')
+                        .map(str::trim)
+                        .filter(|e| !e.is_empty() && is_selected(Path::new(e)))
+                        .map(str::to_string),
+                ),
+                Err(e) => {
+                    println!("Failed to open file {}, reason {e}", folder.display());
+                    process::exit(1);
+                }
             }
         }
-    } else {
-        files_to_check = match fs::read_to_string(&settings.folder_with_files_to_check) {
-            Ok(t) => t.split('\n').map(str::trim).map(str::to_string).filter(|e| e.ends_with(".svg")).collect(),
-            Err(e) => {
-                println!("Failed to open file {}, reason {}", settings.folder_with_files_to_check, e);
-                process::exit(1);
-            }
-        };
     }
+    // Sorted by relative path, so order doesn't depend on file system or location of checked folder
+    files_to_check.sort_by_cached_key(|e| relative_file_name(settings, e));
     println!("Collected {} files to check", files_to_check.len());
+    select_files(settings, files_to_check)
+}
+
+// Sampling is done before sharding, so shards with the same seed split the same sample
+fn select_files(settings: &Settings, mut files_to_check: Vec<String>) -> Vec<String> {
+    if settings.sample_files != 0 && settings.sample_files < files_to_check.len() {
+        let mut rng = StdRng::seed_from_u64(settings.sample_seed);
+        let mut indexes = rand::seq::index::sample(&mut rng, files_to_check.len(), settings.sample_files).into_vec();
+        indexes.sort_unstable();
+        files_to_check = indexes.into_iter().map(|e| files_to_check[e].clone()).collect();
+        println!("Selected {} random files with seed {}", files_to_check.len(), settings.sample_seed);
+    }
+    // Shard is validated when loading settings
+    if let Some((index, count)) = parse_shard(&settings.shard).unwrap() {
+        files_to_check = files_to_check.into_iter().skip(index - 1).step_by(count).collect();
+        println!("Selected {} files from shard {index}/{count}", files_to_check.len());
+    }
     files_to_check
 }
 
-fn check_tools(settings: &Settings) {
-    if settings.first_tool_path.contains('/') {
-        if !Path::new(&settings.first_tool_path).is_file() {
-            eprintln!("First tool not found at {}", settings.first_tool_path);
-            process::exit(1);
-        }
+fn check_tool_exists(name: &str, path: &str) {
+    let exists = if path.contains('/') {
+        Path::new(path).is_file()
     } else {
-        let path = which::which(&settings.first_tool_path);
-        if path.is_err() {
-            eprintln!("First tool not found at {}", settings.first_tool_path);
-            process::exit(1);
-        }
+        which::which(path).is_ok()
+    };
+    if !exists {
+        eprintln!("Tool {name} not found at {path}");
+        process::exit(1);
     }
+}
 
-    if settings.other_tool_path.contains('/') {
-        if !Path::new(&settings.other_tool_path).is_file() {
-            eprintln!("Other tool not found at {}", settings.other_tool_path);
-            process::exit(1);
-        }
-    } else {
-        let path = which::which(&settings.other_tool_path);
-        if path.is_err() {
-            eprintln!("Other tool not found at {}", settings.other_tool_path);
-            process::exit(1);
-        }
+fn check_tools(settings: &Settings) {
+    for tool in &settings.tools {
+        check_tool_exists(&tool.name, &tool.path);
     }
 
-    // Check if tools png output is same - should be different
-    if settings.first_tool_png_name_ending == settings.other_tool_png_name_ending {
-        eprintln!("First tool and other tool png name ending is same, should be different, because otherwise it will overwrite each other, and will give always same results");
+    if settings.tools.len() < 2 {
+        eprintln!("At least 2 tools must be set, to be able to compare results");
         process::exit(1);
     }
 }
 
-fn test_hashers() {
+#[allow(dead_code)]
+fn test_hashers(settings: &Settings) {
     let hash_algs = [
         HashAlg::VertGradient,
         HashAlg::DoubleGradient,
@@ -113,7 +165,7 @@ fn test_hashers() {
     ];
     // let hash_algs = [HashAlg::VertGradient];
 
-    let files = find_files(&load_settings(), ".png");
+    let files = find_files(settings, ".png");
     // Remove "_rsvg" and "_thorvg" from file names
     let mut files_cleaned = files
         .iter()
@@ -137,7 +189,7 @@ fn test_hashers() {
     for (_thorvg_file_name, thorvg_image, _rsvg_file_name, rsvg_image) in dynamic_images {
         for alg in hash_algs {
             for remove_alpha in [true, false] {
-                let diff = *get_difference_between_images(&[alg], &mut thorvg_image.clone(), &mut rsvg_image.clone(), remove_alpha)
+                let diff = *get_difference_between_images(&[alg], &thorvg_image, &rsvg_image, remove_alpha)
                     .first()
                     .unwrap();
                 let key = format!("{:?}-{}", alg, remove_alpha);
@@ -154,26 +206,15 @@ fn test_hashers() {
 }
 
 fn main() {
-    // test_hashers();
-
-    let settings = load_settings();
-    check_tools(&settings);
-
-    // Comment to test svg files
-    if settings.lottie_test {
-        test_lottie(&settings);
-        return;
-    } else if settings.thorvg_test {
-        test_thorvg(&settings);
-        return;
+    let cli = Cli::parse();
+    let mut settings = load_settings(&cli.config, &cli.all_overrides());
+    let mode = cli.mode.unwrap_or_default();
+    if !matches!(mode, Mode::Reduce { .. } | Mode::PruneCache { .. }) {
+        prepare_input_folders(&mut settings);
     }
 
-    let mut files_to_check = find_files(&settings, ".svg");
-    assert!(!files_to_check.is_empty());
+    // test_hashers(&settings);
 
-    if settings.limit_files != 0 {
-        files_to_check = files_to_check[..settings.limit_files].to_vec();
-    }
     if settings.limit_threads != 0 {
         rayon::ThreadPoolBuilder::new()
             .num_threads(settings.limit_threads as usize)
@@ -181,110 +222,66 @@ fn main() {
             .unwrap();
     }
 
-    let atomic: AtomicI32 = AtomicI32::new(0);
-    let broken_items: AtomicU32 = AtomicU32::new(0);
-    let problematic_items: AtomicU32 = AtomicU32::new(0);
-    let ignored_files: AtomicU32 = AtomicU32::new(0);
-    // Remove output files if exists
-    if settings.remove_files_from_output_folder_at_start {
-        let _ = fs::remove_dir_all(&settings.output_folder);
-        let _ = fs::remove_dir_all(&settings.problematic_files_path);
-        let _ = fs::remove_dir_all(&settings.ignored_files_path);
+    if cli.update_baseline && settings.baseline_file.is_empty() {
+        eprintln!("`baseline_file` must be set in settings, to be able to update baseline");
+        process::exit(1);
     }
-    let _ = fs::create_dir_all(&settings.output_folder);
-    let _ = fs::create_dir_all(&settings.problematic_files_path);
-    let _ = fs::create_dir_all(&settings.ignored_files_path);
-
-    files_to_check.par_iter().for_each(|source_file| {
-        let number = atomic.fetch_add(1, Ordering::Relaxed);
-        if number % 100 == 0 {
-            println!("-- {}/{}", number, files_to_check.len());
-        }
 
-        if settings.ignore_thorvg_not_supported_items {
-            if let Ok(t) = fs::read_to_string(source_file) {
-                if [
-                    "<foreignObject", // https://github.com/thorvg/thorvg/issues/1255#issuecomment-2019804429
-                    "<pattern", // https://github.com/thorvg/thorvg/issues/1255#issuecomment-2019804429
-                    "<switch",// https://github.com/thorvg/thorvg/issues/1255#issuecomment-2019804429
-                    "x:href", // https://github.com/thorvg/thorvg/issues/1255#issuecomment-2019804429
-                    "mix-blend-mode:multiply", // https://github.com/thorvg/thorvg/issues/1255#issuecomment-2019804429
-                    "<text",     // Fonts are not implemented
-                    "<filter",   // Filters are not implemented in thorvg - but some may be implemented
-                    "<image",    // Used by me build not support images, but can in future
-                    "<!ENTITY", // <!ENTITY> is a xml std and it's not widely supported inside svg. https://github.com/thorvg/thorvg/issues/1255#issuecomment-1537563461
-                    "<flowRoot", // this was a feature described in one of the svg tiny versions pkt 4, but was removed from the latter versions. - https://github.com/thorvg/thorvg/issues/1255#issuecomment-1537563461 -  https://www.w3.org/TR/2004/WD-SVG12-20041027/
-                ]
-                    .iter()
-                    .any(|e| t.contains(e))
-                    // Nested svg files are not supported - https://github.com/thorvg/thorvg/issues/1255#issuecomment-1537563461
-                    || t.matches("<svg").count() > 1
-                {
-                    // println!("Ignoring {} with text", source_file);
-                    ignored_files.fetch_add(1, Ordering::Relaxed);
-                    let new_file_name = format!(
-                        "{}/{}",
-                        settings.ignored_files_path,
-                        Path::new(&source_file).file_name().unwrap().to_string_lossy()
-                    );
-                    fs::copy(source_file, new_file_name).unwrap();
-                    if settings.remove_ignored_files_after_copying {
-                        let _ = fs::remove_file(source_file);
-                    }
-                    return;
-                }
-            }
+    match mode {
+        Mode::Compare => {
+            check_tools(&settings);
+            compare_svg_files(&settings, cli.update_baseline, cli.resume);
         }
-
-        let first_output_png = source_file.replace(".svg", &settings.first_tool_png_name_ending);
-        let other_output_png = source_file.replace(".svg", &settings.other_tool_png_name_ending);
-
-        if !settings.ignore_conversion_step && !convert_svg_to_png(&settings, source_file, &first_output_png, &other_output_png, &problematic_items) {
-            return;
+        Mode::Thorvg => {
+            check_tool_exists("thorvg", &settings.thorvg_path);
+            test_thorvg(&settings, cli.resume);
         }
-
-        if !settings.ignore_similarity_checking_step {
-            compare_images(
-                source_file, &first_output_png, &other_output_png, &settings, &broken_items, &problematic_items,
-            );
+        Mode::Lottie => {
+            check_tool_exists("lottie", &settings.lottie_path);
+            test_lottie(&settings, cli.resume);
         }
-    });
-
-    remove_output_png_files(&settings);
-
-    if ignored_files.load(Ordering::Relaxed) > 0 {
-        println!("Ignored {} files", ignored_files.load(Ordering::Relaxed));
-    }
-    if broken_items.load(Ordering::Relaxed) > 0 || problematic_items.load(Ordering::Relaxed) > 0 {
-        eprintln!(
-            "POSSIBLE_PROBLEM - Found {} files that looks different and {} files that cannot be tested",
-            broken_items.load(Ordering::Relaxed),
-            problematic_items.load(Ordering::Relaxed)
-        );
-        if settings.return_error_when_finding_invalid_files {
-            process::exit(1);
+        Mode::Golden { update_goldens } => {
+            if settings.golden_folder.is_empty() || settings.tools.is_empty() {
+                eprintln!("`golden_folder` and at least one [[tool]] must be set in settings, to use golden mode");
+                process::exit(1);
+            }
+            let tool = settings.golden_tool();
+            check_tool_exists(&tool.name, &tool.path);
+            test_golden(&settings, update_goldens, cli.update_baseline, cli.resume);
         }
-    } else {
-        println!("Not found any problematic files");
-    }
-}
-
-fn remove_output_png_files(settings: &Settings) {
-    if !settings.remove_generated_png_files_at_end {
-        return;
-    }
-
-    for entry in WalkDir::new(&settings.folder_with_files_to_check).into_iter().flatten() {
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
+        Mode::Bisect {
+            repository,
+            build_command,
+            good,
+            bad,
+        } => {
+            // Binary built inside repository may not exist yet, it is checked after first build
+            if settings.tools.len() < 2 {
+                eprintln!("At least 2 tools must be set, to be able to compare results");
+                process::exit(1);
+            }
+            // Paths are made absolute, so e.g. `./thorvg/build/svg2png` is found inside `thorvg`
+            let repository_path = absolute_path(&repository);
+            let is_inside_repository = |path: &str| path.contains('/') && Path::new(&absolute_path(path)).starts_with(&repository_path);
+            for tool in settings.tools.iter().filter(|e| !is_inside_repository(&e.path)) {
+                check_tool_exists(&tool.name, &tool.path);
+            }
+            let options = BisectOptions {
+                repository,
+                build_command,
+                good,
+                bad,
+            };
+            bisect_files(&settings, &options);
         }
-        let full_path = match path.canonicalize() {
-            Ok(t) => t.to_string_lossy().to_string(),
-            Err(_) => continue,
-        };
-        if full_path.ends_with(".png") {
-            let _ = fs::remove_file(full_path);
+        Mode::Reduce { file, thorvg, output } => {
+            if thorvg {
+                check_tool_exists("thorvg", &settings.thorvg_path);
+            } else {
+                check_tools(&settings);
+            }
+            reduce_file(&settings, &ReduceOptions { file, thorvg, output });
         }
+        Mode::PruneCache { max_age_days } => prune_render_cache(&settings, max_age_days),
     }
 }
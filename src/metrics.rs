@@ -0,0 +1,247 @@
+use std::fmt;
+
+use image::RgbaImage;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::setting::{MetricCombination, Settings};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    // Minimal hamming distance between perceptual hashes
+    Hash,
+    DifferingPixels,
+    MaxChannelDelta,
+    Psnr,
+    Ssim,
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Hash => "hash",
+            Self::DifferingPixels => "differing_pixels",
+            Self::MaxChannelDelta => "max_channel_delta",
+            Self::Psnr => "psnr",
+            Self::Ssim => "ssim",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ImageMetrics {
+    pub hash_difference: u32,
+    // Number of pixels, in which any channel differs more than `pixel_tolerance`
+    pub differing_pixels: u64,
+    pub max_channel_delta: u8,
+    pub rmse: f64,
+    // Infinite for identical images, saved as null in json
+    #[serde(deserialize_with = "deserialize_psnr")]
+    pub psnr: f64,
+    pub ssim: f64,
+}
+
+fn deserialize_psnr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+}
+
+impl ImageMetrics {
+    pub fn exceeded_metrics(&self, settings: &Settings) -> Vec<Metric> {
+        settings
+            .broken_metrics
+            .iter()
+            .copied()
+            .filter(|metric| match metric {
+                Metric::Hash => self.hash_difference > settings.max_difference,
+                Metric::DifferingPixels => self.differing_pixels > settings.max_differing_pixels,
+                Metric::MaxChannelDelta => self.max_channel_delta > settings.max_channel_delta,
+                Metric::Psnr => self.psnr < settings.min_psnr,
+                Metric::Ssim => self.ssim < settings.min_ssim,
+            })
+            .collect()
+    }
+
+    pub fn value(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Hash => f64::from(self.hash_difference),
+            Metric::DifferingPixels => self.differing_pixels as f64,
+            Metric::MaxChannelDelta => f64::from(self.max_channel_delta),
+            Metric::Psnr => self.psnr,
+            Metric::Ssim => self.ssim,
+        }
+    }
+
+    pub fn is_broken(&self, settings: &Settings) -> bool {
+        let exceeded = self.exceeded_metrics(settings);
+        match settings.broken_metrics_combination {
+            MetricCombination::Any => !exceeded.is_empty(),
+            MetricCombination::All => exceeded.len() == settings.broken_metrics.len(),
+        }
+    }
+}
+
+impl fmt::Display for ImageMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "hash {}, differing pixels {}, max channel delta {}, rmse {:.3}, psnr {:.2}, ssim {:.4}",
+            self.hash_difference, self.differing_pixels, self.max_channel_delta, self.rmse, self.psnr, self.ssim
+        )
+    }
+}
+
+// Both images must have same size, alpha channel is ignored
+pub fn get_pixel_metrics(first_image: &RgbaImage, second_image: &RgbaImage, pixel_tolerance: u8) -> (u64, u8, f64, f64) {
+    let mut differing_pixels = 0;
+    let mut max_channel_delta = 0;
+    let mut squared_error_sum = 0.0;
+    for (first_pixel, second_pixel) in first_image.pixels().zip(second_image.pixels()) {
+        let mut pixel_max_delta = 0;
+        for channel in 0..3 {
+            let delta = first_pixel.0[channel].abs_diff(second_pixel.0[channel]);
+            pixel_max_delta = pixel_max_delta.max(delta);
+            squared_error_sum += f64::from(delta) * f64::from(delta);
+        }
+        if pixel_max_delta > pixel_tolerance {
+            differing_pixels += 1;
+        }
+        max_channel_delta = max_channel_delta.max(pixel_max_delta);
+    }
+
+    let values = (u64::from(first_image.width()) * u64::from(first_image.height()) * 3).max(1);
+    let mse = squared_error_sum / values as f64;
+    let rmse = mse.sqrt();
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    };
+    (differing_pixels, max_channel_delta, rmse, psnr)
+}
+
+const SSIM_WINDOW: u32 = 8;
+
+// Mean SSIM of luma, calculated in not overlapping 8x8 windows
+pub fn get_ssim(first_image: &RgbaImage, second_image: &RgbaImage) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    let luma = |image: &RgbaImage, x: u32, y: u32| {
+        let p = image.get_pixel(x, y).0;
+        0.299 * f64::from(p[0]) + 0.587 * f64::from(p[1]) + 0.114 * f64::from(p[2])
+    };
+
+    let (width, height) = first_image.dimensions();
+    let mut ssim_sum = 0.0;
+    let mut windows = 0;
+    for window_y in (0..height).step_by(SSIM_WINDOW as usize) {
+        for window_x in (0..width).step_by(SSIM_WINDOW as usize) {
+            let window_width = SSIM_WINDOW.min(width - window_x);
+            let window_height = SSIM_WINDOW.min(height - window_y);
+            let n = f64::from(window_width * window_height);
+
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in window_y..window_y + window_height {
+                for x in window_x..window_x + window_width {
+                    let a = luma(first_image, x, y);
+                    let b = luma(second_image, x, y);
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                }
+            }
+            let mean_a = sum_a / n;
+            let mean_b = sum_b / n;
+            let variance_a = sum_aa / n - mean_a * mean_a;
+            let variance_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+
+            ssim_sum += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        return 1.0;
+    }
+    ssim_sum / f64::from(windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn gray_image() -> RgbaImage {
+        RgbaImage::from_pixel(10, 10, Rgba([100, 100, 100, 255]))
+    }
+
+    fn metrics(hash_difference: u32, differing_pixels: u64) -> ImageMetrics {
+        ImageMetrics {
+            hash_difference,
+            differing_pixels,
+            max_channel_delta: 0,
+            rmse: 0.0,
+            psnr: f64::INFINITY,
+            ssim: 1.0,
+        }
+    }
+
+    #[test]
+    fn identical_images_have_no_difference() {
+        let (differing_pixels, max_channel_delta, rmse, psnr) = get_pixel_metrics(&gray_image(), &gray_image(), 0);
+        assert_eq!((differing_pixels, max_channel_delta), (0, 0));
+        assert_eq!(rmse, 0.0);
+        assert_eq!(psnr, f64::INFINITY);
+        assert!((get_ssim(&gray_image(), &gray_image()) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn measures_single_different_pixel() {
+        let mut other_image = gray_image();
+        other_image.put_pixel(3, 4, Rgba([130, 100, 100, 255]));
+
+        let (differing_pixels, max_channel_delta, rmse, psnr) = get_pixel_metrics(&gray_image(), &other_image, 0);
+        assert_eq!((differing_pixels, max_channel_delta), (1, 30));
+        // Squared error 900 of one channel divided by 100 pixels with 3 channels
+        assert!((rmse - 3.0_f64.sqrt()).abs() < 1e-9);
+        assert!((psnr - 10.0 * (255.0 * 255.0 / 3.0_f64).log10()).abs() < 1e-9);
+        assert!(get_ssim(&gray_image(), &other_image) < 1.0);
+
+        let (differing_pixels, ..) = get_pixel_metrics(&gray_image(), &other_image, 30);
+        assert_eq!(differing_pixels, 0);
+    }
+
+    #[test]
+    fn ignores_alpha_channel() {
+        let mut other_image = gray_image();
+        other_image.put_pixel(0, 0, Rgba([100, 100, 100, 0]));
+        let (differing_pixels, max_channel_delta, ..) = get_pixel_metrics(&gray_image(), &other_image, 0);
+        assert_eq!((differing_pixels, max_channel_delta), (0, 0));
+    }
+
+    #[test]
+    fn combines_exceeded_metrics() {
+        let mut settings = Settings {
+            broken_metrics: vec![Metric::Hash, Metric::DifferingPixels],
+            max_difference: 5,
+            max_differing_pixels: 10,
+            ..Settings::default()
+        };
+        let only_hash_exceeded = metrics(6, 10);
+        let both_exceeded = metrics(6, 11);
+        assert_eq!(only_hash_exceeded.exceeded_metrics(&settings), [Metric::Hash]);
+        assert_eq!(both_exceeded.exceeded_metrics(&settings), [Metric::Hash, Metric::DifferingPixels]);
+
+        settings.broken_metrics_combination = MetricCombination::Any;
+        assert!(only_hash_exceeded.is_broken(&settings));
+        assert!(!metrics(5, 10).is_broken(&settings));
+
+        settings.broken_metrics_combination = MetricCombination::All;
+        assert!(!only_hash_exceeded.is_broken(&settings));
+        assert!(both_exceeded.is_broken(&settings));
+    }
+}
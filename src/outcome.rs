@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ignore_rules::IgnoreMatch;
+use crate::metrics::{ImageMetrics, Metric};
+use crate::runner::{ProcessOutput, ProcessStatus};
+use crate::sanitizer::find_sanitizer_report;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    // Tool could not be started at all e.g. binary not exists or is not executable
+    FailedToRun { reason: String },
+    NonZeroExit { code: i32 },
+    Signal { signal: i32 },
+    Timeout,
+    MemoryLimitExceeded,
+    CpuLimitExceeded,
+    SanitizerReport { sanitizer: String },
+    MissingOutput,
+    // Tool in `directory` output mode created more than one file matching `output_glob`, so it is not known which one is its render
+    MultipleOutputs { files: Vec<String> },
+    // Reference png used in golden mode not exists
+    MissingReference { path: String },
+    UndecodablePng { reason: String },
+    SizeMismatch { first: (u32, u32), other: (u32, u32) },
+    VisualDifference { exceeded: Vec<Metric> },
+    // Tool was not run or its failure was suppressed by ignore rule limited to some tools, so only pairs with it are skipped
+    Ignored { rule: String, reason: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Passed,
+    Ignored,
+    // Tool produced results, but they looks different
+    Broken,
+    // Tool crashed or failed to produce valid results, so it was not possible to compare them
+    Problematic,
+}
+
+impl Outcome {
+    // Sanitizer report is more important than exit status, because sanitizers may be configured to not abort program
+    pub fn from_process(output: &ProcessOutput) -> Self {
+        if let Some(sanitizer) = find_sanitizer_report(&output.all_output()) {
+            return Self::SanitizerReport {
+                sanitizer: sanitizer.to_string(),
+            };
+        }
+        match output.status {
+            ProcessStatus::Exited(0) => Self::Success,
+            ProcessStatus::Exited(code) => Self::NonZeroExit { code },
+            ProcessStatus::Signaled(signal) => Self::Signal { signal },
+            ProcessStatus::Timeout => Self::Timeout,
+            ProcessStatus::MemoryLimitExceeded => Self::MemoryLimitExceeded,
+            ProcessStatus::CpuLimitExceeded => Self::CpuLimitExceeded,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == Self::Success
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Self::Success => Category::Passed,
+            Self::VisualDifference { .. } => Category::Broken,
+            Self::Ignored { .. } => Category::Ignored,
+            _ => Category::Problematic,
+        }
+    }
+
+    // Short, stable name, used to group outcomes
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::FailedToRun { .. } => "failed_to_run",
+            Self::NonZeroExit { .. } => "non_zero_exit",
+            Self::Signal { .. } => "signal",
+            Self::Timeout => "timeout",
+            Self::MemoryLimitExceeded => "memory_limit_exceeded",
+            Self::CpuLimitExceeded => "cpu_limit_exceeded",
+            Self::SanitizerReport { .. } => "sanitizer_report",
+            Self::MissingOutput => "missing_output",
+            Self::MultipleOutputs { .. } => "multiple_outputs",
+            Self::MissingReference { .. } => "missing_reference",
+            Self::UndecodablePng { .. } => "undecodable_png",
+            Self::SizeMismatch { .. } => "size_mismatch",
+            Self::VisualDifference { .. } => "visual_difference",
+            Self::Ignored { .. } => "ignored",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Success => write!(f, "success"),
+            Self::FailedToRun { reason } => write!(f, "failed to run - {reason}"),
+            Self::NonZeroExit { code } => write!(f, "exited with code {code}"),
+            Self::Signal { signal } => write!(f, "killed by signal {signal}"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::MemoryLimitExceeded => write!(f, "memory limit probably exceeded - failed after using most of it"),
+            Self::CpuLimitExceeded => write!(f, "cpu time limit exceeded"),
+            Self::SanitizerReport { sanitizer } => write!(f, "{sanitizer} report"),
+            Self::MissingOutput => write!(f, "png file was not generated"),
+            Self::MultipleOutputs { files } => write!(f, "generated {} files matching output glob - {}", files.len(), files.join(", ")),
+            Self::MissingReference { path } => write!(f, "reference png {path} not exists"),
+            Self::UndecodablePng { reason } => write!(f, "failed to decode png - {reason}"),
+            Self::SizeMismatch { first, other } => write!(
+                f,
+                "non equal sizes {}x{} and {}x{} - diff {}x{}",
+                first.0,
+                first.1,
+                other.0,
+                other.1,
+                first.0.abs_diff(other.0),
+                first.1.abs_diff(other.1)
+            ),
+            Self::VisualDifference { exceeded } => write!(
+                f,
+                "images looks different - exceeded {}",
+                exceeded.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+            ),
+            Self::Ignored { reason, .. } => write!(f, "ignored - {reason}"),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ToolResult {
+    pub tool: String,
+    pub outcome: Outcome,
+    // Not available when tool was killed or not run at all
+    pub exit_code: Option<i32>,
+    #[serde(serialize_with = "serialize_duration", deserialize_with = "deserialize_duration")]
+    pub duration: Option<Duration>,
+    // Captured stdout and stderr of tool
+    pub output: String,
+}
+
+impl ToolResult {
+    // Used when conversion step is skipped, and png files generated earlier are used
+    pub fn skipped(tool: &str) -> Self {
+        Self {
+            tool: tool.to_string(),
+            outcome: Outcome::Success,
+            exit_code: None,
+            duration: None,
+            output: String::new(),
+        }
+    }
+
+    pub fn failed_to_run(tool: &str, error: &io::Error) -> Self {
+        Self {
+            tool: tool.to_string(),
+            outcome: Outcome::FailedToRun { reason: error.to_string() },
+            exit_code: None,
+            duration: None,
+            output: String::new(),
+        }
+    }
+
+    // Used when tool is not run, because file uses features not supported by it
+    pub fn ignored(tool: &str, ignored: IgnoreMatch) -> Self {
+        let mut tool_result = Self::skipped(tool);
+        tool_result.ignore(ignored);
+        tool_result
+    }
+
+    // Output of failed tool is kept, to show which failure was suppressed
+    pub fn ignore(&mut self, ignored: IgnoreMatch) {
+        self.outcome = Outcome::Ignored {
+            rule: ignored.rule,
+            reason: ignored.reason,
+        };
+    }
+}
+
+// Duration is saved as number of seconds
+fn serialize_duration<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.map(|e| e.as_secs_f64()).serialize(serializer)
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.map(Duration::from_secs_f64))
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PairResult {
+    pub first_tool: String,
+    pub other_tool: String,
+    pub outcome: Outcome,
+    // Not available when images have different sizes
+    pub metrics: Option<ImageMetrics>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct FileResult {
+    pub file: String,
+    // Set when file was extracted from archive or .svgz file, because `file` is removed at exit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // When set, file was not checked at all or its failure was suppressed
+    pub ignore_reason: Option<String>,
+    // Name of ignore rule which matched file
+    pub ignore_rule: Option<String>,
+    // Features used by svg file e.g. `<filter` or `@transform`
+    pub features: Vec<String>,
+    pub tools: Vec<ToolResult>,
+    pub pairs: Vec<PairResult>,
+}
+
+impl FileResult {
+    pub fn new(file: &str, source: Option<String>) -> Self {
+        Self {
+            file: file.to_string(),
+            source,
+            ignore_reason: None,
+            ignore_rule: None,
+            features: Vec::new(),
+            tools: Vec::new(),
+            pairs: Vec::new(),
+        }
+    }
+
+    // Name used in reports, which points to file that still exists after run
+    pub fn name(&self) -> &str {
+        self.source.as_deref().unwrap_or(&self.file)
+    }
+
+    pub fn ignore(&mut self, ignored: IgnoreMatch) {
+        self.ignore_reason = Some(ignored.reason);
+        self.ignore_rule = Some(ignored.rule);
+    }
+
+    // Worst category of all tool and pair outcomes
+    pub fn category(&self) -> Category {
+        if self.ignore_reason.is_some() {
+            return Category::Ignored;
+        }
+        self.outcomes().map(Outcome::category).max().unwrap_or(Category::Passed)
+    }
+
+    pub fn outcomes(&self) -> impl Iterator<Item = &Outcome> {
+        self.tools.iter().map(|e| &e.outcome).chain(self.pairs.iter().map(|e| &e.outcome))
+    }
+}
+
+pub struct Summary {
+    pub files: usize,
+    pub categories: BTreeMap<Category, usize>,
+    // Number of occurrences of each outcome, grouped by tool or pair of tools
+    pub outcomes: BTreeMap<(String, &'static str), usize>,
+}
+
+impl Summary {
+    pub fn new(results: &[FileResult]) -> Self {
+        let mut categories = BTreeMap::new();
+        let mut outcomes = BTreeMap::new();
+        for result in results {
+            *categories.entry(result.category()).or_insert(0) += 1;
+            for tool in &result.tools {
+                *outcomes.entry((tool.tool.clone(), tool.outcome.kind())).or_insert(0) += 1;
+            }
+            for pair in &result.pairs {
+                *outcomes
+                    .entry((format!("{} vs {}", pair.first_tool, pair.other_tool), pair.outcome.kind()))
+                    .or_insert(0) += 1;
+            }
+        }
+        Self {
+            files: results.len(),
+            categories,
+            outcomes,
+        }
+    }
+
+    pub fn count(&self, category: Category) -> usize {
+        self.categories.get(&category).copied().unwrap_or_default()
+    }
+
+    pub fn print(&self) {
+        println!("Checked {} files", self.files);
+        for ((name, kind), count) in &self.outcomes {
+            if *kind != "success" {
+                println!("{name} - {kind}: {count}");
+            }
+        }
+        if self.count(Category::Ignored) > 0 {
+            println!("Ignored {} files", self.count(Category::Ignored));
+        }
+    }
+}
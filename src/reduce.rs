@@ -0,0 +1,363 @@
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::process::{self, Command};
+
+use roxmltree::{Document, NodeType};
+
+use crate::features::parsing_options;
+use crate::image_comparison::{compare_images, load_image};
+use crate::outcome::Outcome;
+use crate::runner::{run_command, ProcessLimits};
+use crate::sanitizer::SanitizerReport;
+use crate::scratch::{scratch_folder, RenderJob};
+use crate::setting::Settings;
+use crate::svg_2_png::render_with_tool_quietly;
+use crate::thorvg::THORVG_ARGUMENTS;
+
+const PATH_COMMANDS: &str = "MmLlHhVvCcSsQqTtAaZz";
+
+pub struct ReduceOptions {
+    pub file: String,
+    // File is checked like in thorvg mode, instead of rendering it with all tools
+    pub thorvg: bool,
+    pub output: Option<String>,
+}
+
+// Outcome kind of every failed tool and pair of tools, with sanitizer error type and top stack frame when available
+#[derive(PartialEq, Eq)]
+struct FailureSignature(Vec<(String, &'static str, Option<String>)>);
+
+#[derive(Clone, Copy)]
+enum Pass {
+    Nodes,
+    Attributes,
+    PathCommands,
+}
+
+struct Reducer<'a> {
+    settings: &'a Settings,
+    thorvg: bool,
+    expected: FailureSignature,
+    // Candidates are written here, because tools create png files next to svg file
+    scratch_file: String,
+    output_file: String,
+    tests: usize,
+}
+
+pub fn reduce_file(settings: &Settings, options: &ReduceOptions) {
+    let content = fs::read_to_string(&options.file).unwrap_or_else(|e| exit_with_error(&format!("Failed to read {} - {e}", options.file)));
+
+    // Scratch folder is removed at exit, also when reducing fails
+    let scratch_folder = scratch_folder().join("reduce");
+    if let Err(e) = fs::create_dir_all(&scratch_folder) {
+        exit_with_error(&format!("Failed to create scratch folder {} - {e}", scratch_folder.display()));
+    }
+    let output_file = options.output.clone().unwrap_or_else(|| {
+        let output_folder = if options.thorvg {
+            &settings.thorvg_broken_files_path
+        } else {
+            &settings.output_folder
+        };
+        let _ = fs::create_dir_all(output_folder);
+        let file_stem = Path::new(&options.file).file_stem().unwrap_or_default().to_string_lossy();
+        format!("{output_folder}/{file_stem}_reduced.svg")
+    });
+
+    let mut reducer = Reducer {
+        settings,
+        thorvg: options.thorvg,
+        expected: FailureSignature(Vec::new()),
+        scratch_file: scratch_folder.join("reduced.svg").to_string_lossy().to_string(),
+        output_file,
+        tests: 0,
+    };
+    let expected = reducer.signature(&content);
+    if expected.0.is_empty() {
+        exit_with_error(&format!("File {} not fails, so there is nothing to reduce", options.file));
+    }
+    println!("Reducing {} ({} bytes) with failure signature {expected}", options.file, content.len());
+    reducer.expected = expected;
+
+    let reduced = reducer.reduce(content.clone());
+    if reduced.len() == content.len() {
+        println!("Failed to reduce file, after {} tests", reducer.tests);
+        return;
+    }
+    println!(
+        "Reduced file from {} to {} bytes, after {} tests, saved to {}",
+        content.len(),
+        reduced.len(),
+        reducer.tests,
+        reducer.output_file
+    );
+}
+
+impl Reducer<'_> {
+    // Passes are repeated, because e.g. removing attributes may allow to remove elements which used them
+    fn reduce(&mut self, mut content: String) -> String {
+        loop {
+            let size = content.len();
+            for pass in [Pass::Nodes, Pass::Attributes, Pass::PathCommands] {
+                content = self.reduce_pass(content, pass);
+            }
+            if content.len() == size {
+                return self.remove_whitespace(content);
+            }
+        }
+    }
+
+    // Whitespace between elements is removed at once, at the end, because testing it piece by piece is not worth it
+    fn remove_whitespace(&mut self, content: String) -> String {
+        let ranges = match Document::parse_with_options(&content, parsing_options()) {
+            Ok(document) => document
+                .root()
+                .descendants()
+                .filter(|e| e.is_text() && e.text().unwrap_or_default().trim().is_empty())
+                .map(|e| e.range())
+                .collect::<Vec<_>>(),
+            Err(_) => return content,
+        };
+        let candidate = remove_ranges(&content, &ranges, &[]);
+        if candidate.len() == content.len() || self.signature(&candidate) != self.expected {
+            return content;
+        }
+        let _ = fs::write(&self.output_file, &candidate);
+        candidate
+    }
+
+    // Simplified delta debugging - removes chunks of items, and when no chunk can be removed, chunks are halved
+    fn reduce_pass(&mut self, content: String, pass: Pass) -> String {
+        let ranges = match Document::parse_with_options(&content, parsing_options()) {
+            Ok(document) => removable_ranges(&document, &content, pass),
+            // Original file may be invalid xml, in which case only tools can decide what is inside
+            Err(_) => return content,
+        };
+        let mut kept = (0..ranges.len()).collect::<Vec<_>>();
+        let mut current_size = content.len();
+        let mut chunk_size = kept.len().div_ceil(2);
+        while chunk_size > 0 {
+            let mut start = 0;
+            let mut removed_any = false;
+            while start < kept.len() {
+                let end = (start + chunk_size).min(kept.len());
+                let candidate_kept = kept[..start].iter().chain(&kept[end..]).copied().collect::<Vec<_>>();
+                let candidate = remove_ranges(&content, &ranges, &candidate_kept);
+                // Items may be already removed together with their parent
+                if candidate.len() == current_size {
+                    kept = candidate_kept;
+                    continue;
+                }
+                if self.signature(&candidate) == self.expected {
+                    let _ = fs::write(&self.output_file, &candidate);
+                    println!("Reduced to {} bytes", candidate.len());
+                    current_size = candidate.len();
+                    kept = candidate_kept;
+                    removed_any = true;
+                } else {
+                    start = end;
+                }
+            }
+            if !removed_any {
+                chunk_size /= 2;
+            }
+            chunk_size = chunk_size.min(kept.len());
+        }
+        remove_ranges(&content, &ranges, &kept)
+    }
+
+    fn signature(&mut self, content: &str) -> FailureSignature {
+        self.tests += 1;
+        if let Err(e) = fs::write(&self.scratch_file, content) {
+            exit_with_error(&format!("Failed to write {} - {e}", self.scratch_file));
+        }
+        if self.thorvg {
+            self.thorvg_signature()
+        } else {
+            self.tools_signature()
+        }
+    }
+
+    // Same command as in thorvg mode
+    fn thorvg_signature(&self) -> FailureSignature {
+        let limits = ProcessLimits::from_settings(self.settings);
+        let output = run_command(
+            Command::new(&self.settings.thorvg_path).arg(&self.scratch_file).args(THORVG_ARGUMENTS),
+            &limits,
+        )
+        .unwrap_or_else(|e| exit_with_error(&format!("Failed to execute thorvg - {e}")));
+        let outcome = Outcome::from_process(&output);
+        if outcome.is_success() {
+            return FailureSignature(Vec::new());
+        }
+        FailureSignature(vec![("thorvg".to_string(), outcome.kind(), sanitizer_signature(&output.all_output()))])
+    }
+
+    // Same checks as in compare mode, but without copying files to output folders
+    fn tools_signature(&self) -> FailureSignature {
+        let mut failures = Vec::new();
+        let mut images = Vec::new();
+        let job = RenderJob::create();
+        for tool in &self.settings.tools {
+            let output_png = job.output_png(&self.scratch_file, tool);
+            let (tool_result, _) = render_with_tool_quietly(self.settings, tool, &self.scratch_file, &output_png);
+            // Such failure not depends on content of file, so it cannot be reduced
+            if let Outcome::FailedToRun { reason } = &tool_result.outcome {
+                exit_with_error(&format!("Failed to run {} - {reason}", tool.name));
+            }
+            let image = if tool_result.outcome.is_success() {
+                load_image(&output_png)
+            } else {
+                Err(tool_result.outcome)
+            };
+            match image {
+                Ok(image) => images.push(Some(image)),
+                Err(outcome) => {
+                    failures.push((tool.name.clone(), outcome.kind(), sanitizer_signature(&tool_result.output)));
+                    images.push(None);
+                }
+            }
+        }
+        for (first, other) in self.settings.tool_pairs() {
+            let (Some(first_image), Some(other_image)) = (&images[first], &images[other]) else {
+                continue;
+            };
+            let (outcome, _) = compare_images(first_image, other_image, self.settings);
+            if !outcome.is_success() {
+                let subject = format!("{} vs {}", self.settings.tools[first].name, self.settings.tools[other].name);
+                failures.push((subject, outcome.kind(), None));
+            }
+        }
+        FailureSignature(failures)
+    }
+}
+
+impl fmt::Display for FailureSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures = self
+            .0
+            .iter()
+            .map(|(subject, kind, sanitizer_signature)| match sanitizer_signature {
+                Some(sanitizer_signature) => format!("{subject} - {kind} ({sanitizer_signature})"),
+                None => format!("{subject} - {kind}"),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", failures.join(", "))
+    }
+}
+
+fn sanitizer_signature(output: &str) -> Option<String> {
+    SanitizerReport::parse(output).map(|e| e.signature(1))
+}
+
+// Byte ranges in content, that can be removed independently of each other in given pass
+fn removable_ranges(document: &Document, content: &str, pass: Pass) -> Vec<Range<usize>> {
+    let root_element = document.root_element();
+    let nodes = document.root().descendants().filter(|e| *e != document.root());
+    match pass {
+        // Whitespace between elements is not worth testing
+        Pass::Nodes => nodes
+            .filter(|e| *e != root_element)
+            .filter(|e| e.node_type() != NodeType::Text || !e.text().unwrap_or_default().trim().is_empty())
+            .map(|e| e.range())
+            .collect(),
+        Pass::Attributes => nodes.flat_map(|e| e.attributes()).map(|e| e.range()).collect(),
+        Pass::PathCommands => nodes
+            .filter_map(|e| e.attributes().find(|attribute| attribute.name() == "d"))
+            .flat_map(|attribute| {
+                let value_range = attribute.range_value();
+                path_command_ranges(&content[value_range.clone()])
+                    .into_iter()
+                    .map(move |e| value_range.start + e.start..value_range.start + e.end)
+            })
+            .collect(),
+    }
+}
+
+// Each command starts with its letter and ends before next command
+fn path_command_ranges(path: &str) -> Vec<Range<usize>> {
+    let starts = path
+        .char_indices()
+        .filter(|(_, c)| PATH_COMMANDS.contains(*c))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).copied().chain([path.len()]))
+        .map(|(start, end)| *start..end)
+        .collect()
+}
+
+// Ranges may be nested (e.g. element and its child), in which case only outer one is removed
+fn remove_ranges(content: &str, ranges: &[Range<usize>], kept: &[usize]) -> String {
+    let mut kept = kept.iter().peekable();
+    let mut removed = Vec::new();
+    for (idx, range) in ranges.iter().enumerate() {
+        if kept.peek() == Some(&&idx) {
+            kept.next();
+        } else {
+            removed.push(range.clone());
+        }
+    }
+    removed.sort_by_key(|e| e.start);
+
+    let mut result = String::with_capacity(content.len());
+    let mut position = 0;
+    for range in removed {
+        if range.start < position {
+            continue;
+        }
+        result.push_str(&content[position..range.start]);
+        position = range.end;
+    }
+    result.push_str(&content[position..]);
+    result
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_path_to_commands() {
+        let path = "M0 0L10 1e2 h-5Z";
+        let commands = path_command_ranges(path).into_iter().map(|e| &path[e]).collect::<Vec<_>>();
+        assert_eq!(commands, ["M0 0", "L10 1e2 ", "h-5", "Z"]);
+        assert!(path_command_ranges("").is_empty());
+    }
+
+    #[test]
+    fn removes_not_kept_ranges() {
+        let content = "<svg><rect/><circle/><path/></svg>";
+        let ranges = [5..12, 12..21, 21..28];
+        assert_eq!(remove_ranges(content, &ranges, &[0, 1, 2]), content);
+        assert_eq!(remove_ranges(content, &ranges, &[1]), "<svg><circle/></svg>");
+        assert_eq!(remove_ranges(content, &ranges, &[]), "<svg></svg>");
+    }
+
+    #[test]
+    fn removes_only_outer_of_nested_ranges() {
+        let content = "<svg><g><rect/></g><path/></svg>";
+        // `<g>` element, its child and `<path>`
+        let ranges = [5..19, 8..15, 19..26];
+        assert_eq!(remove_ranges(content, &ranges, &[2]), "<svg><path/></svg>");
+        assert_eq!(remove_ranges(content, &ranges, &[0, 2]), "<svg><g></g><path/></svg>");
+        assert_eq!(remove_ranges(content, &ranges, &[1]), "<svg></svg>");
+    }
+
+    #[test]
+    fn finds_ranges_of_path_commands_in_document() {
+        let content = r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0L5 5"/></svg>"#;
+        let Ok(document) = Document::parse_with_options(content, parsing_options()) else {
+            panic!("document is not valid");
+        };
+        let ranges = removable_ranges(&document, content, Pass::PathCommands);
+        assert_eq!(ranges.iter().map(|e| &content[e.clone()]).collect::<Vec<_>>(), ["M0 0", "L5 5"]);
+    }
+}
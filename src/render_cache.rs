@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use walkdir::WalkDir;
+
+use crate::fingerprint::{file_hash, tool_fingerprint};
+use crate::outcome::{Outcome, ToolResult};
+use crate::setting::{Settings, ToolSettings};
+
+// Folder inside `resume_folder`, used when `render_cache_folder` is not set
+const RENDERS_FOLDER: &str = "renders";
+// Saved in folder of each tool build, to show in messages which tool rendered files
+const TOOL_INFO_FILE: &str = "tool.txt";
+
+// Characters of fingerprint used as folder name, enough to not mix different builds of tool
+const FINGERPRINT_CHARS: usize = 16;
+
+#[derive(Default)]
+struct CacheStatistics {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+// Renders of tools, reused when the same svg file is rendered again by unchanged tool
+// Saved as `<tool fingerprint>/<svg hash>.png` with result of tool in `<svg hash>.json`
+pub struct RenderCache {
+    // Empty when cache is disabled
+    folder: String,
+    fingerprints: BTreeMap<String, String>,
+    statistics: BTreeMap<String, CacheStatistics>,
+}
+
+impl RenderCache {
+    pub fn disabled() -> Self {
+        Self {
+            folder: String::new(),
+            fingerprints: BTreeMap::new(),
+            statistics: BTreeMap::new(),
+        }
+    }
+
+    fn new(settings: &Settings, folder: &str, tools: &[&ToolSettings]) -> Self {
+        let mut fingerprints = BTreeMap::new();
+        let mut statistics = BTreeMap::new();
+        for tool in tools {
+            let fingerprint = fingerprint(settings, tool);
+            let tool_folder = format!("{folder}/{}", &fingerprint[..FINGERPRINT_CHARS]);
+            let _ = fs::create_dir_all(&tool_folder);
+            let _ = fs::write(
+                format!("{tool_folder}/{TOOL_INFO_FILE}"),
+                format!("{}\n{}\n{}\n", tool.name, tool.path, tool.arguments),
+            );
+            fingerprints.insert(tool.name.clone(), fingerprint);
+            statistics.insert(tool.name.clone(), CacheStatistics::default());
+        }
+        Self {
+            folder: folder.to_string(),
+            fingerprints,
+            statistics,
+        }
+    }
+
+    // Renders are reused in every run, also without resuming, because they are found by fingerprint of tool, so renders of old builds never match
+    pub fn open(settings: &Settings, tools: &[&ToolSettings]) -> Self {
+        match cache_folder(settings) {
+            Some(folder) => Self::new(settings, &folder, tools),
+            None => Self::disabled(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.folder.is_empty()
+    }
+
+    pub fn fingerprints(&self) -> &BTreeMap<String, String> {
+        &self.fingerprints
+    }
+
+    // Empty when cache is disabled, so file is not read without need
+    pub fn svg_hash(&self, source_file: &str) -> String {
+        if !self.is_enabled() {
+            return String::new();
+        }
+        file_hash(source_file).unwrap_or_default()
+    }
+
+    fn entry_path(&self, tool: &str, svg_hash: &str) -> Option<String> {
+        if !self.is_enabled() || svg_hash.is_empty() {
+            return None;
+        }
+        let fingerprint = self.fingerprints.get(tool)?;
+        Some(format!("{}/{}/{svg_hash}", self.folder, &fingerprint[..FINGERPRINT_CHARS]))
+    }
+
+    // Copies cached render to `output_png`, None when tool not rendered this svg file yet
+    pub fn load(&self, tool: &str, svg_hash: &str, output_png: &str) -> Option<ToolResult> {
+        let entry_path = self.entry_path(tool, svg_hash)?;
+        let tool_result = read_entry(&entry_path, output_png);
+        if let Some(statistics) = self.statistics.get(tool) {
+            let counter = if tool_result.is_some() { &statistics.hits } else { &statistics.misses };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        // Modification time of json is time of last use, so renders not used for long time can be pruned
+        if tool_result.is_some() {
+            let _ = File::options()
+                .write(true)
+                .open(format!("{entry_path}.json"))
+                .and_then(|e| e.set_modified(SystemTime::now()));
+        }
+        tool_result
+    }
+
+    // Timeouts and exceeded limits depends on load of machine, and failure to start tool on its installation, so such results are not saved
+    pub fn save(&self, tool: &str, svg_hash: &str, tool_result: &ToolResult, output_png: &str) {
+        let Some(entry_path) = self.entry_path(tool, svg_hash) else {
+            return;
+        };
+        if matches!(
+            tool_result.outcome,
+            Outcome::FailedToRun { .. } | Outcome::Timeout | Outcome::MemoryLimitExceeded | Outcome::CpuLimitExceeded
+        ) {
+            return;
+        }
+        if let Some(parent) = Path::new(&entry_path).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if tool_result.outcome.is_success() && fs::copy(output_png, format!("{entry_path}.png")).is_err() {
+            return;
+        }
+        // Json is written last, so render without saved png is never used
+        if let Ok(content) = serde_json::to_string(tool_result) {
+            let _ = fs::write(format!("{entry_path}.json"), content);
+        }
+    }
+
+    pub fn print_statistics(&self) {
+        for (tool, statistics) in &self.statistics {
+            let hits = statistics.hits.load(Ordering::Relaxed);
+            let misses = statistics.misses.load(Ordering::Relaxed);
+            if hits + misses > 0 {
+                println!(
+                    "Render cache - {tool}: {hits} hits, {misses} misses ({:.1}% hits)",
+                    hits as f64 * 100.0 / (hits + misses) as f64
+                );
+            }
+        }
+    }
+}
+
+// When `render_cache_folder` is not set, renders are saved in `resume_folder`, None when both are empty
+fn cache_folder(settings: &Settings) -> Option<String> {
+    if !settings.render_cache_folder.is_empty() {
+        Some(settings.render_cache_folder.clone())
+    } else if !settings.resume_folder.is_empty() {
+        Some(format!("{}/{RENDERS_FOLDER}", settings.resume_folder))
+    } else {
+        None
+    }
+}
+
+// Output mode is part of fingerprint, because with wrong mode or glob renders are reported as missing
+fn fingerprint(settings: &Settings, tool: &ToolSettings) -> String {
+    let arguments = format!("{} {:?} {}", tool.arguments, tool.output_mode, tool.output_glob);
+    tool_fingerprint(settings, &tool.path, &arguments)
+}
+
+fn read_entry(entry_path: &str, output_png: &str) -> Option<ToolResult> {
+    let content = fs::read_to_string(format!("{entry_path}.json")).ok()?;
+    let tool_result: ToolResult = serde_json::from_str(&content).ok()?;
+    if tool_result.outcome.is_success() && fs::copy(format!("{entry_path}.png"), output_png).is_err() {
+        return None;
+    }
+    Some(tool_result)
+}
+
+// Removes renders of tool builds not used by any [[tool]] in settings
+// With `max_age_days`, also removes renders of current builds, which were not used for that many days
+pub fn prune_render_cache(settings: &Settings, max_age_days: Option<u64>) {
+    let Some(folder) = cache_folder(settings) else {
+        eprintln!("`render_cache_folder` and `resume_folder` are not set in settings, so there is no render cache to prune");
+        process::exit(1);
+    };
+    let current_builds = settings
+        .tools
+        .iter()
+        .map(|tool| fingerprint(settings, tool)[..FINGERPRINT_CHARS].to_string())
+        .collect::<BTreeSet<_>>();
+    let max_age = max_age_days.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+
+    let mut removed_files = 0;
+    let mut removed_bytes = 0;
+    let tool_folders = fs::read_dir(&folder)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|e| e.is_dir());
+    for tool_folder in tool_folders {
+        let build = tool_folder.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !current_builds.contains(&build) {
+            let tool_info = fs::read_to_string(tool_folder.join(TOOL_INFO_FILE)).unwrap_or_default();
+            let tool_name = tool_info.lines().next().unwrap_or("unknown tool").to_string();
+            let (files, bytes) = folder_size(&tool_folder);
+            match fs::remove_dir_all(&tool_folder) {
+                Ok(()) => {
+                    println!("Removed renders of old build {build} of {tool_name}");
+                    removed_files += files;
+                    removed_bytes += bytes;
+                }
+                Err(e) => eprintln!("Failed to remove {}, reason {e}", tool_folder.display()),
+            }
+            continue;
+        }
+
+        let Some(max_age) = max_age else {
+            continue;
+        };
+        for entry in WalkDir::new(&tool_folder).into_iter().flatten() {
+            let json_path = entry.path();
+            if json_path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            let unused_time = entry
+                .metadata()
+                .ok()
+                .and_then(|e| e.modified().ok())
+                .and_then(|e| e.elapsed().ok())
+                .unwrap_or_default();
+            if unused_time <= max_age {
+                continue;
+            }
+            // Json is removed first, so it never points to missing png
+            for path in [json_path.to_path_buf(), json_path.with_extension("png")] {
+                let size = fs::metadata(&path).map(|e| e.len()).unwrap_or_default();
+                if fs::remove_file(&path).is_ok() {
+                    removed_files += 1;
+                    removed_bytes += size;
+                }
+            }
+        }
+    }
+    let (left_files, left_bytes) = folder_size(Path::new(&folder));
+    println!(
+        "Removed {removed_files} files ({}) from render cache, {left_files} files ({}) left",
+        format_size(removed_bytes),
+        format_size(left_bytes)
+    );
+}
+
+fn folder_size(folder: &Path) -> (usize, u64) {
+    WalkDir::new(folder)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .fold((0, 0), |(files, bytes), entry| {
+            (files + 1, bytes + entry.metadata().map(|e| e.len()).unwrap_or_default())
+        })
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{original_file_name, relative_file_name};
+use crate::fingerprint::{file_hash, hash_bytes};
+use crate::html_report::ReportImages;
+use crate::outcome::FileResult;
+use crate::setting::Settings;
+
+// Settings which only select files or change printed messages, so results of files checked with other values can be reused
+const SETTINGS_NOT_CHANGING_RESULTS: [&str; 9] = [
+    "limit_threads", "limit_files", "sample_files", "sample_seed", "shard", "remove_files_from_output_folder_at_start", "debug_show_always_output",
+    "resume_folder", "render_cache_folder",
+];
+
+#[derive(Deserialize, Serialize)]
+struct LogEntry {
+    // Relative path, so entries are found even if folder with files was moved
+    file: String,
+    file_hash: String,
+    settings_hash: String,
+    // Fingerprint of each tool used to check file
+    fingerprints: BTreeMap<String, String>,
+    result: FileResult,
+    // Saved only for files that not passed, when html report is enabled
+    report_images: Option<ReportImages>,
+}
+
+// Results of checked files are appended to `results_<mode>.jsonl` in `resume_folder` just after checking each file, so they are not lost when app is killed
+// When resuming, files with the same content, checked with the same tools and settings, are not checked again
+pub struct ResultsLog {
+    writer: Option<Mutex<File>>,
+    previous: HashMap<String, LogEntry>,
+    settings_hash: String,
+    fingerprints: BTreeMap<String, String>,
+    // Returns path of file, other than svg file, on which result depends e.g. reference png in golden mode
+    reference_file: Option<fn(&Settings, &str) -> String>,
+    reused_results: AtomicUsize,
+}
+
+impl ResultsLog {
+    pub fn disabled() -> Self {
+        Self {
+            writer: None,
+            previous: HashMap::new(),
+            settings_hash: String::new(),
+            fingerprints: BTreeMap::new(),
+            reference_file: None,
+            reused_results: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn open(settings: &Settings, mode: &str, fingerprints: &BTreeMap<String, String>, resume: bool) -> Self {
+        if settings.resume_folder.is_empty() {
+            if resume {
+                eprintln!("`resume_folder` is not set in settings, so all files will be checked");
+            }
+            return Self::disabled();
+        }
+        let log_file = format!("{}/results_{mode}.jsonl", settings.resume_folder);
+        let _ = fs::create_dir_all(&settings.resume_folder);
+
+        let settings_hash = settings_hash(settings, mode);
+        let mut previous = HashMap::new();
+        // Last line may be incomplete when app was killed while writing it
+        let mut incomplete_line = false;
+        if resume {
+            let content = fs::read_to_string(&log_file).unwrap_or_default();
+            incomplete_line = !content.is_empty() && !content.ends_with('\n');
+            for entry in content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+                .filter(|e| e.settings_hash == settings_hash)
+            {
+                previous.insert(entry.file.clone(), entry);
+            }
+            println!("Loaded results of {} files from {log_file}", previous.len());
+        }
+
+        let writer = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&log_file);
+        let writer = match writer {
+            Ok(mut t) => {
+                // Otherwise first new entry would be appended to incomplete line and lost
+                if incomplete_line {
+                    let _ = t.write_all(b"\n");
+                }
+                Some(Mutex::new(t))
+            }
+            Err(e) => {
+                eprintln!("Failed to open {log_file}, results will not be saved, reason {e}");
+                None
+            }
+        };
+        Self {
+            writer,
+            previous,
+            settings_hash,
+            fingerprints: fingerprints.clone(),
+            reference_file: None,
+            reused_results: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_reference_file(mut self, reference_file: fn(&Settings, &str) -> String) -> Self {
+        self.reference_file = Some(reference_file);
+        self
+    }
+
+    // Hash of svg file and of its reference file, so result is not reused when any of them was changed, added or removed
+    pub fn input_hash(&self, settings: &Settings, source_file: &str) -> String {
+        let svg_hash = file_hash(source_file).unwrap_or_default();
+        match self.reference_file {
+            Some(reference_file) => {
+                let reference_hash = file_hash(&reference_file(settings, source_file)).unwrap_or_default();
+                format!("{svg_hash}:{reference_hash}")
+            }
+            None => svg_hash,
+        }
+    }
+
+    // Returns result and images for html report from previous run, when file was not changed and was checked with the same tools
+    pub fn find(&self, settings: &Settings, source_file: &str, file_hash: &str) -> Option<(FileResult, Option<ReportImages>)> {
+        let entry = self.previous.get(&relative_file_name(settings, source_file))?;
+        if entry.file_hash != file_hash || entry.fingerprints != self.fingerprints {
+            return None;
+        }
+        let mut file_result = entry.result.clone();
+        // Extracted files are saved under different path in each run
+        file_result.file = source_file.to_string();
+        file_result.source = original_file_name(settings, source_file);
+        self.reused_results.fetch_add(1, Ordering::Relaxed);
+        Some((file_result, entry.report_images.clone()))
+    }
+
+    pub fn print_reused_results(&self) {
+        let reused_results = self.reused_results.load(Ordering::Relaxed);
+        if reused_results > 0 {
+            println!("Reused results of {reused_results} files checked in previous run");
+        }
+    }
+
+    pub fn append(&self, settings: &Settings, file_hash: &str, file_result: &FileResult, report_images: Option<&ReportImages>) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let entry = LogEntryRef {
+            file: relative_file_name(settings, &file_result.file),
+            file_hash,
+            settings_hash: &self.settings_hash,
+            fingerprints: &self.fingerprints,
+            result: file_result,
+            report_images,
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+        let mut writer = writer.lock().unwrap();
+        // Whole line is written at once and flushed, so killing app may only cut last line
+        let _ = writer.write_all(line.as_bytes()).and_then(|()| writer.flush());
+    }
+}
+
+// Same as `LogEntry`, but without copying results when saving them
+#[derive(Serialize)]
+struct LogEntryRef<'a> {
+    file: String,
+    file_hash: &'a str,
+    settings_hash: &'a str,
+    fingerprints: &'a BTreeMap<String, String>,
+    result: &'a FileResult,
+    report_images: Option<&'a ReportImages>,
+}
+
+fn settings_hash(settings: &Settings, mode: &str) -> String {
+    let mut settings = serde_json::to_value(settings).unwrap_or_default();
+    if let Some(items) = settings.as_object_mut() {
+        for key in SETTINGS_NOT_CHANGING_RESULTS {
+            items.remove(key);
+        }
+    }
+    hash_bytes(format!("{mode}\n{settings}").as_bytes())
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::setting::Settings;
+
+#[derive(Clone, Copy)]
+pub struct ProcessLimits {
+    pub timeout: Duration,
+    // In megabytes, 0 means no limit
+    pub memory_limit: u64,
+    // In seconds, 0 means no limit
+    pub cpu_time_limit: u64,
+}
+
+impl ProcessLimits {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            timeout: Duration::from_secs(u64::from(settings.timeout)),
+            memory_limit: settings.memory_limit,
+            cpu_time_limit: settings.cpu_time_limit,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProcessStatus {
+    Exited(i32),
+    Signaled(i32),
+    Timeout,
+    MemoryLimitExceeded,
+    CpuLimitExceeded,
+}
+
+impl ProcessStatus {
+    pub fn success(self) -> bool {
+        self == Self::Exited(0)
+    }
+
+    // Not available when process was killed
+    pub fn exit_code(self) -> Option<i32> {
+        match self {
+            Self::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProcessStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exited with code {code}"),
+            Self::Signaled(signal) => write!(f, "killed by signal {signal}"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::MemoryLimitExceeded => write!(f, "memory limit probably exceeded - failed after using most of it"),
+            Self::CpuLimitExceeded => write!(f, "cpu time limit exceeded"),
+        }
+    }
+}
+
+pub struct ProcessOutput {
+    pub status: ProcessStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub duration: Duration,
+}
+
+impl ProcessOutput {
+    pub fn all_output(&self) -> String {
+        format!("{}\n{}", String::from_utf8_lossy(&self.stdout), String::from_utf8_lossy(&self.stderr))
+    }
+}
+
+// Part of memory limit, which process must use to treat its failure as caused by the limit
+// Address space contains also e.g. not loaded parts of libraries, so resident memory never reaches the whole limit
+const MEMORY_LIMIT_USED_PERCENT: u64 = 80;
+
+// `ru_maxrss` is in kilobytes on Linux, and in bytes on macOS
+#[cfg(target_os = "macos")]
+const MAX_RSS_UNIT: u64 = 1;
+#[cfg(not(target_os = "macos"))]
+const MAX_RSS_UNIT: u64 = 1024;
+
+// Resources used by process itself, without its children
+struct ResourceUsage {
+    cpu_time: Duration,
+    // In bytes
+    max_rss: u64,
+}
+
+// Runs command in its own process group, so after timeout all its children can be killed too
+pub fn run_command(command: &mut Command, limits: &ProcessLimits) -> io::Result<ProcessOutput> {
+    let memory_limit = limits.memory_limit * 1024 * 1024;
+    let cpu_time_limit = limits.cpu_time_limit;
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    // SAFETY: only async-signal-safe setrlimit calls are done between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            if memory_limit != 0 {
+                set_limit(libc::RLIMIT_AS, memory_limit, memory_limit)?;
+            }
+            if cpu_time_limit != 0 {
+                // After soft limit SIGXCPU is sent, after hard limit SIGKILL
+                set_limit(libc::RLIMIT_CPU, cpu_time_limit, cpu_time_limit + 1)?;
+            }
+            Ok(())
+        });
+    }
+
+    let start = Instant::now();
+    let mut child = command.spawn()?;
+    let stdout_reader = spawn_reader(child.stdout.take());
+    let stderr_reader = spawn_reader(child.stderr.take());
+
+    let (exit_status, usage, timed_out) = wait_with_timeout(&child, limits.timeout)?;
+    let duration = start.elapsed();
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    let status = if timed_out {
+        ProcessStatus::Timeout
+    } else if let Some(code) = exit_status.code() {
+        ProcessStatus::Exited(code)
+    } else {
+        let signal = exit_status.signal().unwrap_or_default();
+        // SIGKILL is sent also e.g. by OOM killer, so it is blamed on the limit only when process really used that much cpu time
+        let cpu_limit_reached = limits.cpu_time_limit != 0 && usage.cpu_time >= Duration::from_secs(limits.cpu_time_limit);
+        if cpu_limit_reached && (signal == libc::SIGXCPU || signal == libc::SIGKILL) {
+            ProcessStatus::CpuLimitExceeded
+        } else {
+            ProcessStatus::Signaled(signal)
+        }
+    };
+    // After reaching memory limit allocations fail, and process may crash or exit with any code, so it is only guessed from used memory
+    // This is heuristic - single large allocation refused by the limit is not counted in used memory, so it is reported as normal failure,
+    // and unrelated failure of tool which used most of the limit is reported as exceeded limit
+    let memory_limit_reached = memory_limit != 0 && usage.max_rss >= memory_limit / 100 * MEMORY_LIMIT_USED_PERCENT;
+    let status = if memory_limit_reached && !status.success() && matches!(status, ProcessStatus::Exited(_) | ProcessStatus::Signaled(_)) {
+        ProcessStatus::MemoryLimitExceeded
+    } else {
+        status
+    };
+
+    Ok(ProcessOutput {
+        status,
+        stdout,
+        stderr,
+        duration,
+    })
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type LimitResource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type LimitResource = libc::c_int;
+
+fn set_limit(resource: LimitResource, soft: u64, hard: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // SAFETY: limit is valid pointer to initialized structure
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn spawn_reader<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn wait_with_timeout(child: &Child, timeout: Duration) -> io::Result<(ExitStatus, ResourceUsage, bool)> {
+    let deadline = Instant::now() + timeout;
+    let mut sleep_time = Duration::from_millis(1);
+    loop {
+        if let Some((status, usage)) = wait_child(child, libc::WNOHANG)? {
+            // Children started by tool may still be alive and keep pipes open
+            kill_process_group(child);
+            return Ok((status, usage, false));
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(child);
+            let (status, usage) = wait_child(child, 0)?.ok_or_else(|| io::Error::other("Process not exited after killing it"))?;
+            return Ok((status, usage, true));
+        }
+        thread::sleep(sleep_time);
+        sleep_time = (sleep_time * 2).min(Duration::from_millis(50));
+    }
+}
+
+// Same as `Child::try_wait` with `WNOHANG` and `Child::wait` without it, but returns also resources used by child
+// `Child` methods must not be used to wait for child after it, because child is already reaped
+fn wait_child(child: &Child, options: libc::c_int) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    let mut status = 0;
+    // SAFETY: rusage contains only integers, so zeroed value is valid
+    let mut rusage: libc::rusage = unsafe { mem::zeroed() };
+    loop {
+        // SAFETY: status and rusage are valid pointers to initialized values
+        match unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, options, &mut rusage) } {
+            0 => return Ok(None),
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            _ => break,
+        }
+    }
+    let usage = ResourceUsage {
+        cpu_time: timeval_duration(rusage.ru_utime) + timeval_duration(rusage.ru_stime),
+        max_rss: rusage.ru_maxrss as u64 * MAX_RSS_UNIT,
+    };
+    Ok(Some((ExitStatus::from_raw(status), usage)))
+}
+
+fn timeval_duration(time: libc::timeval) -> Duration {
+    Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+}
+
+fn kill_process_group(child: &Child) {
+    // Process group id is same as pid of child, because it was started with process_group(0)
+    let pgid = child.id() as libc::pid_t;
+    // SAFETY: kill is safe to call with any arguments
+    unsafe {
+        libc::kill(-pgid, libc::SIGKILL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_shell(script: &str, memory_limit: u64) -> ProcessOutput {
+        let limits = ProcessLimits {
+            timeout: Duration::from_secs(30),
+            memory_limit,
+            cpu_time_limit: 0,
+        };
+        run_command(Command::new("sh").args(["-c", script]), &limits).unwrap()
+    }
+
+    #[test]
+    fn reports_memory_limit_when_memory_used_gradually() {
+        let output = run_shell(r#"awk 'BEGIN { while (1) a[i++] = sprintf("%1000s", i) }'"#, 64);
+        assert_eq!(output.status, ProcessStatus::MemoryLimitExceeded, "{}", output.all_output());
+    }
+
+    // Refused allocation is not counted in used memory, so such failure is not recognized
+    #[test]
+    fn not_reports_memory_limit_when_single_large_allocation_fails() {
+        let output = run_shell("dd if=/dev/zero of=/dev/null bs=256M count=1", 64);
+        assert_eq!(output.status, ProcessStatus::Exited(1), "{}", output.all_output());
+    }
+
+    #[test]
+    fn reports_exit_code_without_memory_limit() {
+        let output = run_shell("dd if=/dev/zero of=/dev/null bs=256M count=1 2>/dev/null && exit 3", 0);
+        assert_eq!(output.status, ProcessStatus::Exited(3));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::outcome::FileResult;
+
+// Header printed at start of report, with name of sanitizer, also used to recognize that tool failed with sanitizer report
+const REPORT_HEADERS: [(&str, &str); 5] = [
+    ("ERROR: AddressSanitizer: ", "AddressSanitizer"),
+    ("ERROR: LeakSanitizer: ", "LeakSanitizer"),
+    ("WARNING: ThreadSanitizer: ", "ThreadSanitizer"),
+    ("ERROR: UndefinedBehaviorSanitizer: ", "UndefinedBehaviorSanitizer"),
+    ("runtime error: ", "UndefinedBehaviorSanitizer"),
+];
+
+// Frames of sanitizer runtime and allocator are the same in most of reports, so are not useful to distinguish them
+const INTERNAL_FRAME_PREFIXES: [&str; 8] = [
+    "__interceptor_", "__asan", "__lsan", "__tsan", "__ubsan", "__sanitizer", "operator new", "operator delete",
+];
+const INTERNAL_FUNCTIONS: [&str; 4] = ["malloc", "calloc", "realloc", "free"];
+
+pub struct SanitizerReport {
+    pub sanitizer: &'static str,
+    // e.g. `heap-buffer-overflow`, `Direct leak` or `data race`
+    pub error_type: String,
+    // Function and file name of each frame, starting from top of stack, without sanitizer frames
+    pub frames: Vec<String>,
+    // Output starting at report header
+    pub report: String,
+}
+
+// Name of sanitizer which printed report in output
+pub fn find_sanitizer_report(output: &str) -> Option<&'static str> {
+    REPORT_HEADERS
+        .iter()
+        .find(|(header, _)| output.contains(header))
+        .map(|(_, sanitizer)| *sanitizer)
+}
+
+impl SanitizerReport {
+    // Only first report from output is parsed, because next ones are often consequences of first one
+    pub fn parse(output: &str) -> Option<Self> {
+        let lines = output.lines().collect::<Vec<_>>();
+        let (header_idx, header, sanitizer, message) = lines.iter().enumerate().find_map(|(idx, line)| {
+            REPORT_HEADERS.iter().find_map(|(header, sanitizer)| {
+                line.split_once(header)
+                    .map(|(before, message)| (idx, *header, *sanitizer, (before, message)))
+            })
+        })?;
+        let (before_header, message) = message;
+        let report_lines = &lines[header_idx..];
+
+        let error_type = match header {
+            "ERROR: LeakSanitizer: " => report_lines
+                .iter()
+                .find_map(|e| e.trim().split_once(" leak of ").map(|(kind, _)| format!("{kind} leak")))
+                .unwrap_or_else(|| message.trim().to_string()),
+            // UBSan describes error in single line e.g. `signed integer overflow: 2147483647 + 1 cannot be represented in type 'int'`
+            "runtime error: " => normalize(message.split(':').next().unwrap_or_default()),
+            _ => normalize(cut_at_any(message, &[" on ", " at ", " (", " for "])),
+        };
+
+        let mut frames = report_lines
+            .iter()
+            .map(|e| e.trim())
+            .skip_while(|e| !e.starts_with('#'))
+            .take_while(|e| e.starts_with('#'))
+            .filter_map(parse_frame)
+            .filter(|e| !is_internal_frame(e))
+            .collect::<Vec<_>>();
+        // UBSan by default not prints stack trace, but place where error happened is printed before header
+        if frames.is_empty() && header == "runtime error: " {
+            frames.push(file_name(before_header.trim().trim_end_matches(':')));
+        }
+
+        Some(Self {
+            sanitizer,
+            error_type,
+            frames,
+            report: report_lines.join("\n"),
+        })
+    }
+
+    // Sanitizer, error type and top frames, same for reports caused by the same bug
+    pub fn signature(&self, frames_number: usize) -> String {
+        let mut parts = vec![format!("{}: {}", self.sanitizer, self.error_type)];
+        parts.extend(self.frames.iter().take(frames_number).cloned());
+        parts.join(" | ")
+    }
+}
+
+pub struct CrashGroup {
+    pub signature: String,
+    pub files: Vec<String>,
+    // Smallest file, easiest to analyze
+    pub representative: String,
+    // Full report of representative file
+    pub report: String,
+}
+
+// Failed files with the same signature are grouped, files without sanitizer report are grouped by outcome
+pub fn group_crashes(results: &[FileResult], frames_number: usize) -> Vec<CrashGroup> {
+    let mut groups: BTreeMap<String, Vec<(u64, &str, String)>> = BTreeMap::new();
+    for file_result in results.iter().filter(|e| e.ignore_reason.is_none()) {
+        for tool_result in file_result.tools.iter().filter(|e| !e.outcome.is_success()) {
+            let (signature, report) = match SanitizerReport::parse(&tool_result.output) {
+                Some(report) => (report.signature(frames_number), report.report),
+                None => (tool_result.outcome.to_string(), tool_result.output.clone()),
+            };
+            let size = fs::metadata(&file_result.file).map(|e| e.len()).unwrap_or(u64::MAX);
+            groups.entry(signature).or_default().push((size, file_result.name(), report));
+        }
+    }
+
+    let mut groups = groups
+        .into_iter()
+        .map(|(signature, mut files)| {
+            files.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+            let (_, representative, report) = &files[0];
+            CrashGroup {
+                representative: representative.to_string(),
+                report: report.clone(),
+                signature,
+                files: files.iter().map(|e| e.1.to_string()).collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.files.len().cmp(&a.files.len()).then_with(|| a.signature.cmp(&b.signature)));
+    groups
+}
+
+// Groups are printed shortly and saved with full reports to `crash_groups.txt`
+pub fn save_crash_groups(output_folder: &str, groups: &[CrashGroup]) {
+    if groups.is_empty() {
+        return;
+    }
+    println!("Found {} unique crashes:", groups.len());
+    let mut content = String::new();
+    for (idx, group) in groups.iter().enumerate() {
+        println!("  {} files - {} - e.g. {}", group.files.len(), group.signature, group.representative);
+        let _ = writeln!(
+            content,
+            "Group {} - {} files - {}\nRepresentative file: {}\nFiles:",
+            idx + 1,
+            group.files.len(),
+            group.signature,
+            group.representative
+        );
+        for file in &group.files {
+            let _ = writeln!(content, "  {file}");
+        }
+        let _ = writeln!(content, "Report:\n{}\n", group.report.trim_end());
+    }
+
+    let _ = fs::create_dir_all(output_folder);
+    let file_name = format!("{output_folder}/crash_groups.txt");
+    if let Err(e) = fs::write(&file_name, content) {
+        eprintln!("Failed to save crash groups to {file_name} - {e}");
+    }
+}
+
+// Frame e.g. `#0 0x4f2a in SvgLoader::parse /src/svgLoader.cpp:10:5` or `#1 0x7f2e (/lib/libc.so.6+0x29d90)`,
+// is shortened to function and file name, because addresses and paths are different between builds
+fn parse_frame(line: &str) -> Option<String> {
+    let (_, mut frame) = line.split_once(' ')?;
+    frame = frame.trim();
+    if frame.starts_with("0x") {
+        frame = frame.split_once(' ').map_or("", |e| e.1).trim();
+    }
+    frame = frame.strip_prefix("in ").unwrap_or(frame);
+    // Module with offset, ThreadSanitizer prints it after location
+    if let Some((before, module)) = frame.rsplit_once('(') {
+        if module.contains("+0x") {
+            frame = before.trim();
+            if frame.is_empty() {
+                return Some(file_name(module.split('+').next().unwrap_or_default()));
+            }
+        }
+    }
+    if frame.is_empty() {
+        return None;
+    }
+    Some(match frame.rsplit_once(' ') {
+        Some((function, location)) if location.contains('/') => format!("{function} {}", file_name(location)),
+        _ => frame.to_string(),
+    })
+}
+
+fn is_internal_frame(frame: &str) -> bool {
+    let function = frame.split([' ', '(']).next().unwrap_or_default();
+    INTERNAL_FRAME_PREFIXES.iter().any(|e| frame.starts_with(e)) || INTERNAL_FUNCTIONS.contains(&function)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or_else(|| path.to_string(), |e| e.to_string_lossy().to_string())
+}
+
+fn cut_at_any<'a>(message: &'a str, separators: &[&str]) -> &'a str {
+    let end = separators.iter().filter_map(|e| message.find(e)).min().unwrap_or(message.len());
+    &message[..end]
+}
+
+// Addresses and numbers are different in each report of the same bug
+fn normalize(message: &str) -> String {
+    message
+        .split_whitespace()
+        .map(|word| {
+            if word.starts_with("0x") {
+                "0x?".to_string()
+            } else {
+                word.chars().map(|c| if c.is_ascii_digit() { 'N' } else { c }).collect()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_address_sanitizer_report() {
+        let output = "Rendering file\n\
+            ==1234==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000014 at pc 0x4f2a bp 0x7ffd sp 0x7ffd\n\
+            READ of size 4 at 0x602000000014 thread T0\n    \
+            #0 0x4f2a in __interceptor_memcpy /llvm/asan_interceptors.cpp:22:3\n    \
+            #1 0x4f3b in tvg::SvgLoader::parse(char const*) /src/thorvg/src/loaders/svg/tvgSvgLoader.cpp:3402:12\n    \
+            #2 0x4f4c in main /src/thorvg/examples/svg2png.cpp:120:9\n    \
+            #3 0x7f2e (/lib/x86_64-linux-gnu/libc.so.6+0x29d90)\n\
+            SUMMARY: AddressSanitizer: heap-buffer-overflow";
+        let Some(report) = SanitizerReport::parse(output) else {
+            panic!("report not found");
+        };
+        assert_eq!(report.sanitizer, "AddressSanitizer");
+        assert_eq!(report.error_type, "heap-buffer-overflow");
+        assert_eq!(
+            report.frames,
+            ["tvg::SvgLoader::parse(char const*) tvgSvgLoader.cpp:3402:12", "main svg2png.cpp:120:9", "libc.so.6"]
+        );
+        assert!(report.report.starts_with("==1234==ERROR: AddressSanitizer"));
+        assert_eq!(
+            report.signature(1),
+            "AddressSanitizer: heap-buffer-overflow | tvg::SvgLoader::parse(char const*) tvgSvgLoader.cpp:3402:12"
+        );
+    }
+
+    #[test]
+    fn parses_leak_sanitizer_report() {
+        let output = "==99==ERROR: LeakSanitizer: detected memory leaks\n\n\
+            Direct leak of 40 byte(s) in 1 object(s) allocated from:\n    \
+            #0 0x4a1b in malloc /llvm/asan_malloc_linux.cpp:69:3\n    \
+            #1 0x4a2c in tvg::Shape::gen() /src/tvgShape.cpp:40:3\n";
+        let Some(report) = SanitizerReport::parse(output) else {
+            panic!("report not found");
+        };
+        assert_eq!(report.sanitizer, "LeakSanitizer");
+        assert_eq!(report.error_type, "Direct leak");
+        assert_eq!(report.frames, ["tvg::Shape::gen() tvgShape.cpp:40:3"]);
+    }
+
+    #[test]
+    fn parses_undefined_behavior_without_stack_trace() {
+        let output =
+            "/src/thorvg/src/common/tvgMath.cpp:77:20: runtime error: signed integer overflow: 2147483647 + 1 cannot be represented in type 'int'";
+        let Some(report) = SanitizerReport::parse(output) else {
+            panic!("report not found");
+        };
+        assert_eq!(report.sanitizer, "UndefinedBehaviorSanitizer");
+        assert_eq!(report.error_type, "signed integer overflow");
+        assert_eq!(report.frames, ["tvgMath.cpp:77:20"]);
+    }
+
+    #[test]
+    fn normalizes_numbers_in_error_type() {
+        let output = "==1==ERROR: AddressSanitizer: requested allocation size 0x7fffffff exceeds maximum supported size of 0x10000000000 (thread T0)";
+        let Some(report) = SanitizerReport::parse(output) else {
+            panic!("report not found");
+        };
+        assert_eq!(report.error_type, "requested allocation size 0x? exceeds maximum supported size of 0x?");
+    }
+
+    #[test]
+    fn ignores_output_without_report() {
+        assert!(SanitizerReport::parse("Segmentation fault\nerror: cannot open file").is_none());
+        assert_eq!(find_sanitizer_report("Segmentation fault"), None);
+        assert_eq!(
+            find_sanitizer_report("==5==WARNING: ThreadSanitizer: data race (pid=5)"),
+            Some("ThreadSanitizer")
+        );
+    }
+
+    #[test]
+    fn parses_frames() {
+        assert_eq!(
+            parse_frame("#0 0x4f2a in SvgLoader::parse /src/svgLoader.cpp:10:5").as_deref(),
+            Some("SvgLoader::parse svgLoader.cpp:10:5")
+        );
+        assert_eq!(parse_frame("#1 0x7f2e (/lib/libc.so.6+0x29d90)").as_deref(), Some("libc.so.6"));
+        // ThreadSanitizer frames have no address, and module is printed after location
+        assert_eq!(
+            parse_frame("#0 tvg::TaskScheduler::run /src/tvgTaskScheduler.cpp:88 (svg2png+0x1234)").as_deref(),
+            Some("tvg::TaskScheduler::run tvgTaskScheduler.cpp:88")
+        );
+        assert_eq!(parse_frame("#2 0x4f4c in main").as_deref(), Some("main"));
+        assert_eq!(parse_frame("#3"), None);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::common::png_file_name;
+use crate::setting::ToolSettings;
+
+static SCRATCH_FOLDER: OnceLock<PathBuf> = OnceLock::new();
+static NEXT_JOB: AtomicUsize = AtomicUsize::new(0);
+
+// Created once per run, and removed at exit, even when app exits with `process::exit`
+pub fn scratch_folder() -> &'static Path {
+    SCRATCH_FOLDER.get_or_init(|| {
+        let folder = std::env::temp_dir().join(format!("svg_tester_{}", process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        // SAFETY: registered function only removes scratch folder, and does not unwind
+        unsafe {
+            libc::atexit(remove_scratch_folder);
+        }
+        folder
+    })
+}
+
+extern "C" fn remove_scratch_folder() {
+    if let Some(folder) = SCRATCH_FOLDER.get() {
+        let _ = fs::remove_dir_all(folder);
+    }
+}
+
+// Separate folder in scratch folder, in which single file is rendered, so tools never write to checked folders
+// Only files created while checking file are inside, so whole folder is removed when job is dropped
+pub struct RenderJob {
+    folder: PathBuf,
+}
+
+impl RenderJob {
+    pub fn create() -> Self {
+        let folder = scratch_folder().join("jobs").join(NEXT_JOB.fetch_add(1, Ordering::Relaxed).to_string());
+        if let Err(e) = fs::create_dir_all(&folder) {
+            eprintln!("Failed to create folder {}, reason {e}", folder.display());
+            process::exit(1);
+        }
+        Self { folder }
+    }
+
+    // Keeps name of svg file, so png copied from job folder to output folder is still `<name><png_name_ending>`
+    // Each tool renders in its own subfolder, so tools which save png beside svg file never find or overwrite png of other tool
+    pub fn output_png(&self, source_file: &str, tool: &ToolSettings) -> String {
+        let tool_folder = self.folder.join(&tool.name);
+        if let Err(e) = fs::create_dir_all(&tool_folder) {
+            eprintln!("Failed to create folder {}, reason {e}", tool_folder.display());
+        }
+        tool_folder
+            .join(png_file_name(source_file, &tool.png_name_ending))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    // Tools that save output beside input file get this copy, instead of original file
+    pub fn copy_source(&self, source_file: &str) -> String {
+        copy_to_folder(source_file, &self.folder)
+    }
+}
+
+impl Drop for RenderJob {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.folder);
+    }
+}
+
+// Returns path of copy, file is copied only once, even when multiple tools use it
+pub fn copy_to_folder(source_file: &str, folder: &Path) -> String {
+    let copied_file = folder.join(Path::new(source_file).file_name().unwrap_or_default());
+    if copied_file != Path::new(source_file) && !copied_file.is_file() {
+        if let Err(e) = fs::copy(source_file, &copied_file) {
+            eprintln!("Failed to copy {source_file} to {}, reason {e}", copied_file.display());
+        }
+    }
+    copied_file.to_string_lossy().to_string()
+}
@@ -1,85 +1,606 @@
-use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use config::Config;
+use config::{Config, Environment, FileFormat, Value};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::inputs::{input_names, InputFolder};
+use crate::metrics::Metric;
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+pub const ENV_PREFIX: &str = "SVG_TESTER";
+// Modes in which files are checked, so ignore rules can be used in them
+pub const RULE_MODES: [&str; 4] = ["compare", "golden", "thorvg", "lottie"];
+// Names of tools used in thorvg and lottie modes, which are not set in [[tool]] sections
+pub const CRASH_MODE_TOOLS: [&str; 2] = ["thorvg", "lottie"];
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
 pub struct Settings {
-    pub folder_with_files_to_check: String,
+    // Single folder or list of folders, file with list of files or zip/tar archive can be used instead of folder
+    #[serde(deserialize_with = "deserialize_string_or_list")]
+    pub folder_with_files_to_check: Vec<String>,
+    // When disabled, only files placed directly in folders are checked
+    pub recursive_search: bool,
+    // Glob patterns matched against path relative to folder e.g. `struct/**` or `**/*-01-*.svg`, when empty, all files are checked
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
     pub ignored_files_path: String,
     pub px_size_of_generated_file: u32,
     pub ignore_conversion_step: bool,
-    pub ignore_thorvg_not_supported_items: bool,
     pub max_difference: u32,
+    // Channel difference up to which pixels are still considered as equal
+    pub pixel_tolerance: u8,
+    pub max_differing_pixels: u64,
+    pub max_channel_delta: u8,
+    pub min_psnr: f64,
+    pub min_ssim: f64,
+    // Metrics checked against its thresholds, to decide if images are different
+    pub broken_metrics: Vec<Metric>,
+    pub broken_metrics_combination: MetricCombination,
+    // Image with highlighted differing pixels, saved for each broken pair
+    pub save_diff_images: bool,
+    // Single image with outputs of both tools and diff, placed side by side
+    pub save_composite_images: bool,
+    // Self-contained `index.html` with all not passed files, saved in output folder
+    pub save_html_report: bool,
+    // `results.json` with results of every checked file, saved in output folder
+    pub save_json_results: bool,
+    // `junit.xml` with test case for each checked file, saved in output folder
+    pub save_junit_report: bool,
+    // `feature_statistics.csv` with failure rate of files using each svg feature, saved in output folder
+    pub save_feature_statistics: bool,
+    // File with known failures, when set only differences from it are treated as problems
+    pub baseline_file: String,
+    // Change of score(value of first of `broken_metrics`) bigger than this is reported
+    pub baseline_score_tolerance: f64,
     pub output_folder: String,
     pub limit_threads: u32,
     pub limit_files: usize,
+    // Number of randomly selected files to check, 0 checks all files
+    pub sample_files: usize,
+    pub sample_seed: u64,
+    // Part of files checked in this run e.g. `2/4`, empty checks all files
+    pub shard: String,
+    // Results of checked files and renders of tools are saved there, to be able to continue run with `--resume`, empty disables it
+    pub resume_folder: String,
+    // Renders of tools are saved there and reused in every run, until svg file or tool changes, empty disables it
+    pub render_cache_folder: String,
     pub remove_files_from_output_folder_at_start: bool,
     pub ignore_similarity_checking_step: bool,
     pub debug_show_always_output: bool,
     pub problematic_files_path: String,
     pub return_error_when_finding_invalid_files: bool,
-    pub remove_problematic_files_after_copying: bool,
-    pub remove_broken_files_after_copying: bool,
-    pub remove_generated_png_files_at_end: bool,
-    pub remove_ignored_files_after_copying: bool,
     pub lottie_path: String,
     pub lottie_broken_files_path: String,
-    pub lottie_test: bool,
     pub thorvg_path: String,
     pub thorvg_broken_files_path: String,
-    pub thorvg_test: bool,
+    // Number of top stack frames, which together with error type identify crash reported by sanitizer
+    pub crash_signature_frames: usize,
     pub timeout: u32,
-    pub first_tool_name: String,
-    pub first_tool_path: String,
-    pub first_tool_png_name_ending: String,
-    pub first_tool_arguments: String,
-
-    pub other_tool_name: String,
-    pub other_tool_path: String,
-    pub other_tool_png_name_ending: String,
-    pub other_tool_arguments: String,
-}
-
-pub fn load_settings() -> Settings {
-    let settings = Config::builder().add_source(config::File::with_name("settings")).build().unwrap();
-    let config = settings.try_deserialize::<HashMap<String, HashMap<String, String>>>().unwrap();
-
-    let gs = config["general"].clone();
-    let fts = config["first_tool"].clone();
-    let ots = config["other_tool"].clone();
-    Settings {
-        folder_with_files_to_check: gs["folder_with_files_to_check"].clone(),
-        ignored_files_path: gs["ignored_files_path"].clone(),
-        px_size_of_generated_file: gs["px_size_of_generated_file"].parse().unwrap(),
-        ignore_conversion_step: gs["ignore_conversion_step"].parse().unwrap(),
-        ignore_thorvg_not_supported_items: gs["ignore_thorvg_not_supported_items"].parse().unwrap(),
-        max_difference: gs["max_difference"].parse().unwrap(),
-        output_folder: gs["output_folder"].clone(),
-        limit_files: gs["limit_files"].parse().unwrap(),
-        limit_threads: gs["limit_threads"].parse().unwrap(),
-        remove_files_from_output_folder_at_start: gs["remove_files_from_output_folder_at_start"].parse().unwrap(),
-        ignore_similarity_checking_step: gs["ignore_similarity_checking_step"].parse().unwrap(),
-        problematic_files_path: gs["problematic_files_path"].clone(),
-        return_error_when_finding_invalid_files: gs["return_error_when_finding_invalid_files"].parse().unwrap(),
-        remove_problematic_files_after_copying: gs["remove_problematic_files_after_copying"].parse().unwrap(),
-        remove_broken_files_after_copying: gs["remove_broken_files_after_copying"].parse().unwrap(),
-        remove_generated_png_files_at_end: gs["remove_generated_png_files_at_end"].parse().unwrap(),
-        remove_ignored_files_after_copying: gs["remove_ignored_files_after_copying"].parse().unwrap(),
-        lottie_path: gs["lottie_path"].clone(),
-        lottie_broken_files_path: gs["lottie_broken_files_path"].clone(),
-        lottie_test: gs["lottie_test"].parse().unwrap(),
-        thorvg_path: gs["thorvg_path"].clone(),
-        thorvg_broken_files_path: gs["thorvg_broken_files_path"].clone(),
-        thorvg_test: gs["thorvg_test"].parse().unwrap(),
-
-        timeout: gs["timeout"].parse().unwrap(),
-        first_tool_name: fts["name"].clone(),
-        first_tool_path: fts["path"].clone(),
-        first_tool_png_name_ending: fts["png_name_ending"].clone(),
-        first_tool_arguments: fts["arguments"].clone(),
-        other_tool_name: ots["name"].clone(),
-        other_tool_path: ots["path"].clone(),
-        other_tool_png_name_ending: ots["png_name_ending"].clone(),
-        other_tool_arguments: ots["arguments"].clone(),
-        debug_show_always_output: gs["debug_show_always_output"].parse().unwrap(),
+    // Exceeding it is only guessed from memory used by tool, see `run_command`
+    pub memory_limit: u64,
+    pub cpu_time_limit: u64,
+    // When empty, every tool is compared with every other tool, otherwise all tools are compared only with this one
+    pub reference_tool: String,
+    // Folder with reference png files used in golden mode, with same names as svg files
+    pub golden_folder: String,
+    // Tool used in golden mode, when empty, first tool is used
+    pub golden_tool: String,
+    // Loaded from separate `[[tool]]` sections
+    #[serde(skip_deserializing)]
+    pub tools: Vec<ToolSettings>,
+    // Loaded from separate `[[ignore_rule]]` sections
+    #[serde(skip_deserializing)]
+    pub ignore_rules: Vec<IgnoreRule>,
+    // Filled after loading settings, by extracting archives from `folder_with_files_to_check`
+    #[serde(skip)]
+    pub input_folders: Vec<InputFolder>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            folder_with_files_to_check: Vec::new(),
+            recursive_search: true,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            ignored_files_path: "IgnoredSVG".to_string(),
+            px_size_of_generated_file: 400,
+            ignore_conversion_step: false,
+            max_difference: 5,
+            pixel_tolerance: 0,
+            max_differing_pixels: 0,
+            max_channel_delta: 0,
+            min_psnr: 30.0,
+            min_ssim: 0.95,
+            broken_metrics: vec![Metric::Hash],
+            broken_metrics_combination: MetricCombination::Any,
+            save_diff_images: true,
+            save_composite_images: false,
+            save_html_report: true,
+            save_json_results: true,
+            save_junit_report: true,
+            save_feature_statistics: true,
+            baseline_file: String::new(),
+            baseline_score_tolerance: 5.0,
+            output_folder: "BrokenSVG".to_string(),
+            limit_threads: 0,
+            limit_files: 0,
+            sample_files: 0,
+            sample_seed: 0,
+            shard: String::new(),
+            resume_folder: "ResumeData".to_string(),
+            render_cache_folder: String::new(),
+            remove_files_from_output_folder_at_start: true,
+            ignore_similarity_checking_step: false,
+            debug_show_always_output: false,
+            problematic_files_path: "ProblematicSVG".to_string(),
+            return_error_when_finding_invalid_files: false,
+            lottie_path: String::new(),
+            lottie_broken_files_path: "BrokenLottie".to_string(),
+            thorvg_path: String::new(),
+            thorvg_broken_files_path: "BrokenThorvg".to_string(),
+            crash_signature_frames: 3,
+            timeout: 120,
+            memory_limit: 0,
+            cpu_time_limit: 0,
+            reference_tool: String::new(),
+            golden_folder: String::new(),
+            golden_tool: String::new(),
+            tools: Vec::new(),
+            ignore_rules: Vec::new(),
+            input_folders: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricCombination {
+    // Images are different, when at least one metric exceeds its threshold
+    Any,
+    // Images are different, only when all metrics exceed their thresholds
+    All,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct ToolSettings {
+    pub name: String,
+    pub path: String,
+    // When empty, `_<name>.png` is used
+    pub png_name_ending: String,
+    pub arguments: String,
+    // Where tool saves png, when not set, `file` is used when arguments contain `{OUTPUT_FILE}`, otherwise `directory`
+    pub output_mode: OutputMode,
+    // Used in `directory` mode, glob matched against names of files created by tool, `{NAME}` is replaced by name of svg file without extension
+    pub output_glob: String,
+    // Used to get version of tool, which is saved in results, when empty `--version` is used
+    pub version_arguments: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    // Replaced by one of other modes when loading settings
+    #[default]
+    Auto,
+    // Tool saves png to path passed in `{OUTPUT_FILE}`
+    File,
+    // Tool prints png to standard output
+    Stdout,
+    // Tool saves png beside svg file or in working directory, png is found with `output_glob`
+    Directory,
+}
+
+// Files matching rule are ignored, instead of being reported as broken or problematic
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct IgnoreRule {
+    pub name: String,
+    // Url of issue, which describes why files are ignored
+    pub issue: String,
+    // Checked before rendering, file is ignored when uses any of these features(e.g. `<filter`, `@xlink:href` or
+    // `mix-blend-mode:multiply`), at least `input_min_count` times
+    pub input_features_any: Vec<String>,
+    pub input_min_count: usize,
+    // Checked when tool failed, file is ignored when output of tool contains all of these items
+    pub output_contains_all: Vec<String>,
+    // Names of tools(`thorvg` and `lottie` in crash modes) and modes to which rule applies, when empty, rule applies to all of them
+    pub tools: Vec<String>,
+    pub modes: Vec<String>,
+}
+
+impl Default for IgnoreRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            issue: String::new(),
+            input_features_any: Vec::new(),
+            input_min_count: 1,
+            output_contains_all: Vec::new(),
+            tools: Vec::new(),
+            modes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrList {
+    String(String),
+    List(Vec<String>),
+}
+
+// Allows to use single value in settings file, where list is expected
+fn deserialize_string_or_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(item) => vec![item],
+        StringOrList::List(items) => items,
+    })
+}
+
+pub fn build_glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| e.to_string())?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+// Returns 1-based index of shard and number of shards, None when files are not split
+pub fn parse_shard(shard: &str) -> Result<Option<(usize, usize)>, String> {
+    if shard.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("invalid shard `{shard}`, expected `index/count` e.g. `1/4`");
+    let (index, count) = shard.split_once('/').ok_or_else(invalid)?;
+    let index = index.trim().parse::<usize>().map_err(|_| invalid())?;
+    let count = count.trim().parse::<usize>().map_err(|_| invalid())?;
+    if count == 0 || !(1..=count).contains(&index) {
+        return Err(format!("shard index must be in range 1..={count}, found `{shard}`"));
+    }
+    Ok(Some((index, count)))
+}
+
+pub struct SettingsError {
+    pub file: String,
+    pub section: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.section.is_empty(), self.key.is_empty()) {
+            (true, _) => write!(f, "{}: {}", self.file, self.message),
+            (false, true) => write!(f, "{}: [{}] {}", self.file, self.section, self.message),
+            (false, false) => write!(f, "{}: [{}] `{}` - {}", self.file, self.section, self.key, self.message),
+        }
+    }
+}
+
+pub fn load_settings(file: &str, overrides: &[String]) -> Settings {
+    match try_load_settings(file, overrides) {
+        Ok(settings) => settings,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{error}");
+            }
+            process::exit(1);
+        }
+    }
+}
+
+// Values are taken from settings file, next from `SVG_TESTER_<SECTION>__<KEY>` environment variables and at the end from `--set section.key=value` arguments
+pub fn try_load_settings(file: &str, overrides: &[String]) -> Result<Settings, Vec<SettingsError>> {
+    let error = |section: &str, key: &str, message: String| SettingsError {
+        file: file.to_string(),
+        section: section.to_string(),
+        key: key.to_string(),
+        message,
+    };
+
+    let mut builder = Config::builder()
+        .add_source(config::File::new(file, FileFormat::Toml))
+        .add_source(Environment::with_prefix(ENV_PREFIX).prefix_separator("_").separator("__"));
+    for item in overrides {
+        let Some((key, value)) = item.split_once('=') else {
+            return Err(vec![error("", "", format!("invalid override `{item}`, expected `section.key=value`"))]);
+        };
+        let value = value.trim();
+        // Lists are written as `[first,second]`
+        builder = if let Some(items) = value.strip_prefix('[').and_then(|e| e.strip_suffix(']')) {
+            let items = items
+                .split(',')
+                .map(|e| e.trim().trim_matches('"').to_string())
+                .filter(|e| !e.is_empty())
+                .collect::<Vec<_>>();
+            builder.set_override(key.trim(), items)
+        } else {
+            builder.set_override(key.trim(), value)
+        }
+        .map_err(|e| vec![error("", "", e.to_string())])?;
+    }
+    let config = builder.build().map_err(|e| vec![error("", "", e.to_string())])?;
+    let mut root = config.cache.into_table().map_err(|e| vec![error("", "", e.to_string())])?;
+
+    for section in root.keys() {
+        if section != "general" && section != "tool" && section != "ignore_rule" {
+            eprintln!("WARNING: {file}: unknown section [{section}] will be ignored");
+        }
+    }
+
+    let Some(general) = root.remove("general") else {
+        return Err(vec![error("general", "", "section is missing".to_string())]);
+    };
+    let mut settings: Settings = deserialize_section(file, "general", general).map_err(|e| vec![e])?;
+
+    if let Some(tools) = root.remove("tool") {
+        let tools = tools
+            .into_array()
+            .map_err(|e| vec![error("tool", "", format!("must be list of [[tool]] sections - {e}"))])?;
+        for (idx, tool) in tools.into_iter().enumerate() {
+            let mut tool: ToolSettings = deserialize_section(file, &format!("tool #{}", idx + 1), tool).map_err(|e| vec![e])?;
+            if tool.png_name_ending.is_empty() {
+                tool.png_name_ending = format!("_{}.png", tool.name);
+            }
+            if tool.output_mode == OutputMode::Auto {
+                tool.output_mode = if tool.arguments.contains("{OUTPUT_FILE}") {
+                    OutputMode::File
+                } else {
+                    OutputMode::Directory
+                };
+            }
+            if tool.output_glob.is_empty() {
+                tool.output_glob = "{NAME}.png".to_string();
+            }
+            if tool.version_arguments.is_empty() {
+                tool.version_arguments = "--version".to_string();
+            }
+            settings.tools.push(tool);
+        }
+    }
+    if let Some(rules) = root.remove("ignore_rule") {
+        let rules = rules
+            .into_array()
+            .map_err(|e| vec![error("ignore_rule", "", format!("must be list of [[ignore_rule]] sections - {e}"))])?;
+        for (idx, rule) in rules.into_iter().enumerate() {
+            let rule = deserialize_section(file, &format!("ignore_rule #{}", idx + 1), rule).map_err(|e| vec![e])?;
+            settings.ignore_rules.push(rule);
+        }
+    }
+
+    let errors = validate_settings(&settings, file);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(settings)
+}
+
+fn deserialize_section<T: DeserializeOwned>(file: &str, section: &str, value: Value) -> Result<T, SettingsError> {
+    let mut track = serde_path_to_error::Track::new();
+    let deserializer = serde_path_to_error::Deserializer::new(value, &mut track);
+    let result = serde_ignored::deserialize(deserializer, |path| {
+        eprintln!("WARNING: {file}: [{section}] unknown key `{path}` will be ignored");
+    });
+    result.map_err(|e| {
+        // Config adds its own location to message, which is less precise than tracked path
+        let mut message = e.to_string();
+        if let Some(idx) = message.find(" for key `") {
+            message.truncate(idx);
+        }
+        SettingsError {
+            file: file.to_string(),
+            section: section.to_string(),
+            key: track.path().to_string().trim_start_matches('.').to_string(),
+            message,
+        }
+    })
+}
+
+fn validate_settings(settings: &Settings, file: &str) -> Vec<SettingsError> {
+    let mut errors = Vec::new();
+    let mut error = |section: &str, key: &str, message: &str| {
+        errors.push(SettingsError {
+            file: file.to_string(),
+            section: section.to_string(),
+            key: key.to_string(),
+            message: message.to_string(),
+        });
+    };
+
+    if settings.folder_with_files_to_check.is_empty() || settings.folder_with_files_to_check.iter().any(String::is_empty) {
+        error("general", "folder_with_files_to_check", "must be set");
+    }
+    let inputs = settings
+        .folder_with_files_to_check
+        .iter()
+        .map(|e| Path::new(e).canonicalize().unwrap_or_else(|_| PathBuf::from(e)))
+        .collect::<Vec<_>>();
+    for (idx, input) in inputs.iter().enumerate() {
+        if inputs[..idx].contains(input) {
+            error(
+                "general",
+                "folder_with_files_to_check",
+                &format!("`{}` is listed more than once", settings.folder_with_files_to_check[idx]),
+            );
+        }
+    }
+    let input_names = input_names(&settings.folder_with_files_to_check);
+    for (idx, name) in input_names.iter().enumerate() {
+        let Some(name) = name else {
+            continue;
+        };
+        if input_names[..idx].iter().flatten().any(|e| e == name) {
+            error(
+                "general",
+                "folder_with_files_to_check",
+                &format!(
+                    "`{}` would be reported with the same name `{name}` as other input",
+                    settings.folder_with_files_to_check[idx]
+                ),
+            );
+        }
+    }
+    for (key, patterns) in [
+        ("include_patterns", &settings.include_patterns),
+        ("exclude_patterns", &settings.exclude_patterns),
+    ] {
+        if let Err(e) = build_glob_set(patterns) {
+            error("general", key, &e);
+        }
+    }
+    if let Err(e) = parse_shard(&settings.shard) {
+        error("general", "shard", &e);
+    }
+    if settings.px_size_of_generated_file == 0 {
+        error("general", "px_size_of_generated_file", "must be bigger than 0");
+    }
+    if settings.broken_metrics.is_empty() {
+        error("general", "broken_metrics", "at least one metric must be set");
+    }
+    if !(-1.0..=1.0).contains(&settings.min_ssim) {
+        error("general", "min_ssim", "must be in range -1.0..=1.0");
+    }
+    if settings.timeout == 0 {
+        error("general", "timeout", "must be bigger than 0");
+    }
+    if !settings.reference_tool.is_empty() && !settings.tools.iter().any(|e| e.name == settings.reference_tool) {
+        error(
+            "general",
+            "reference_tool",
+            &format!("tool `{}` is not found in list of [[tool]] sections", settings.reference_tool),
+        );
+    }
+    if !settings.golden_tool.is_empty() && !settings.tools.iter().any(|e| e.name == settings.golden_tool) {
+        error(
+            "general",
+            "golden_tool",
+            &format!("tool `{}` is not found in list of [[tool]] sections", settings.golden_tool),
+        );
+    }
+
+    for (idx, tool) in settings.tools.iter().enumerate() {
+        let section = format!("tool #{}", idx + 1);
+        for (key, value) in [("name", &tool.name), ("path", &tool.path), ("arguments", &tool.arguments)] {
+            if value.is_empty() {
+                error(&section, key, "must be set");
+            }
+        }
+        if !tool.arguments.contains("{FILE}") {
+            error(&section, "arguments", "must contain {FILE} placeholder");
+        }
+        // Otherwise png would be searched in different place than tool saves it
+        match (tool.output_mode, tool.arguments.contains("{OUTPUT_FILE}")) {
+            (OutputMode::File, false) => error(
+                &section, "arguments", "must contain {OUTPUT_FILE} placeholder, when `output_mode` is `file`",
+            ),
+            (OutputMode::Stdout | OutputMode::Directory, true) => error(
+                &section, "arguments", "must not contain {OUTPUT_FILE} placeholder, when `output_mode` is `stdout` or `directory`",
+            ),
+            _ => {}
+        }
+        if let Err(e) = build_glob_set(&[tool.output_glob.replace("{NAME}", "name")]) {
+            error(&section, "output_glob", &e);
+        }
+        // Tools with same png output would overwrite each other, and will give always same results
+        for other_tool in &settings.tools[..idx] {
+            if tool.name == other_tool.name {
+                error(&section, "name", &format!("`{}` is already used by other tool", tool.name));
+            }
+            if tool.png_name_ending == other_tool.png_name_ending {
+                error(
+                    &section,
+                    "png_name_ending",
+                    &format!("`{}` is already used by tool `{}`", tool.png_name_ending, other_tool.name),
+                );
+            }
+        }
+    }
+
+    for (idx, rule) in settings.ignore_rules.iter().enumerate() {
+        let section = format!("ignore_rule #{}", idx + 1);
+        if rule.name.is_empty() {
+            error(&section, "name", "must be set");
+        }
+        if settings.ignore_rules[..idx].iter().any(|e| e.name == rule.name) {
+            error(&section, "name", &format!("`{}` is already used by other rule", rule.name));
+        }
+        if rule.input_features_any.is_empty() == rule.output_contains_all.is_empty() {
+            error(&section, "", "exactly one of `input_features_any` and `output_contains_all` must be set");
+        }
+        if rule.input_min_count == 0 {
+            error(&section, "input_min_count", "must be bigger than 0");
+        }
+        for mode in &rule.modes {
+            if !RULE_MODES.contains(&mode.as_str()) {
+                error(
+                    &section,
+                    "modes",
+                    &format!("unknown mode `{mode}`, expected one of {}", RULE_MODES.join(", ")),
+                );
+            }
+        }
+        for tool in &rule.tools {
+            if !CRASH_MODE_TOOLS.contains(&tool.as_str()) && !settings.tools.iter().any(|e| &e.name == tool) {
+                error(&section, "tools", &format!("tool `{tool}` is not found in list of [[tool]] sections"));
+            }
+        }
+    }
+
+    errors
+}
+
+impl Settings {
+    // Tool whose results are compared with reference images in golden mode
+    pub fn golden_tool(&self) -> &ToolSettings {
+        if self.golden_tool.is_empty() {
+            &self.tools[0]
+        } else {
+            self.tools.iter().find(|e| e.name == self.golden_tool).unwrap()
+        }
+    }
+
+    // Indexes of tools whose results should be compared with each other
+    pub fn tool_pairs(&self) -> Vec<(usize, usize)> {
+        if self.reference_tool.is_empty() {
+            let mut pairs = Vec::new();
+            for first in 0..self.tools.len() {
+                for other in (first + 1)..self.tools.len() {
+                    pairs.push((first, other));
+                }
+            }
+            pairs
+        } else {
+            let reference = self.tools.iter().position(|e| e.name == self.reference_tool).unwrap();
+            (0..self.tools.len()).filter(|e| *e != reference).map(|e| (reference, e)).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_shard() {
+        assert_eq!(parse_shard(""), Ok(None));
+        assert_eq!(parse_shard("1/4"), Ok(Some((1, 4))));
+        assert_eq!(parse_shard(" 4 / 4 "), Ok(Some((4, 4))));
+    }
+
+    #[test]
+    fn rejects_invalid_shard() {
+        for shard in ["1", "a/4", "1/b", "-1/4", "1/4/2"] {
+            assert!(parse_shard(shard).is_err_and(|e| e.starts_with("invalid shard")), "{shard}");
+        }
+        for shard in ["0/4", "5/4", "1/0"] {
+            assert!(parse_shard(shard).is_err_and(|e| e.starts_with("shard index must be")), "{shard}");
+        }
     }
 }
@@ -1,96 +1,222 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::mem;
 use std::path::Path;
-use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::process::Command;
+use std::time::SystemTime;
 
-use crate::common::save_problematic_file;
-use crate::setting::Settings;
+use globset::Glob;
+use walkdir::WalkDir;
 
-pub fn convert_svg_to_png(
+use crate::common::{absolute_path, png_beside_svg, resolve_program};
+use crate::outcome::{Outcome, ToolResult};
+use crate::render_cache::RenderCache;
+use crate::runner::{run_command, ProcessLimits};
+use crate::scratch::{copy_to_folder, RenderJob};
+use crate::setting::{OutputMode, Settings, ToolSettings};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Same as `render_with_tool`, but render is taken from cache when available, `svg_hash` is empty when cache is disabled
+pub fn render_with_cache(
     settings: &Settings,
+    render_cache: &RenderCache,
+    tool: &ToolSettings,
     source_file: &str,
-    first_output_png: &str,
-    other_output_png: &str,
-    problematic_items: &AtomicU32,
-) -> bool {
-    let possible_output_png_original = source_file.replace(".svg", ".png"); // Usually png files just are created automatically by changing extensions
-
-    let first_command = generate_command_from_items(
-        &settings.first_tool_path, &settings.first_tool_arguments, source_file, &possible_output_png_original, settings.px_size_of_generated_file,
-        settings.timeout,
-    );
-    let other_command = generate_command_from_items(
-        &settings.other_tool_path, &settings.other_tool_arguments, source_file, &possible_output_png_original, settings.px_size_of_generated_file,
-        settings.timeout,
-    );
+    svg_hash: &str,
+    output_png: &str,
+) -> ToolResult {
+    if let Some(tool_result) = render_cache.load(&tool.name, svg_hash, output_png) {
+        return tool_result;
+    }
+    let tool_result = render_with_tool(settings, tool, source_file, output_png);
+    render_cache.save(&tool.name, svg_hash, &tool_result, output_png);
+    tool_result
+}
 
-    for (mut command, output_png, tool_name) in [
-        (first_command, &first_output_png, &settings.first_tool_name),
-        (other_command, &other_output_png, &settings.other_tool_name),
-    ] {
-        // Run command to convert svg to png
-        let output = command
-            .stderr(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .unwrap()
-            .wait_with_output()
-            .unwrap();
-
-        // If converted png file have same name as svg, rename it to required name
-        if Path::new(&possible_output_png_original).is_file() {
-            fs::copy(&possible_output_png_original, output_png)
-                .unwrap_or_else(|_| panic!("Failed to copy file {possible_output_png_original} to {output_png}"));
-            fs::remove_file(&possible_output_png_original).unwrap_or_else(|_| panic!("Failed to remove file {possible_output_png_original}"));
-        }
+// Renders are saved in job folder, when conversion step is skipped, png files placed earlier beside svg file are used
+pub fn output_png(settings: &Settings, job: &RenderJob, source_file: &str, tool: &ToolSettings) -> String {
+    if settings.ignore_conversion_step {
+        png_beside_svg(source_file, &tool.png_name_ending)
+    } else {
+        job.output_png(source_file, tool)
+    }
+}
 
-        let err_message = String::from_utf8(output.stderr);
-        let normal_message = String::from_utf8(output.stdout);
+pub fn render_with_tool(settings: &Settings, tool: &ToolSettings, source_file: &str, output_png: &str) -> ToolResult {
+    let (tool_result, command) = render_with_tool_quietly(settings, tool, source_file, output_png);
+    if !tool_result.outcome.is_success() && !settings.debug_show_always_output {
+        println!(
+            "\n\n{}\ncommand {:?} {:?} - {}",
+            tool_result.output,
+            command.get_program(),
+            command.get_args(),
+            tool_result.outcome
+        );
+    }
+    tool_result
+}
+
+// Failures are not printed, used when the same file is rendered many times, returns also used command
+pub fn render_with_tool_quietly(settings: &Settings, tool: &ToolSettings, source_file: &str, output_png: &str) -> (ToolResult, Command) {
+    let limits = ProcessLimits::from_settings(settings);
+    // Png from previous run would hide that tool not generated file
+    let _ = fs::remove_file(output_png);
+    let output_folder = Path::new(output_png).parent().unwrap_or(Path::new("."));
+
+    // In `directory` mode, tool gets copy of svg placed in folder of output png and is run there, to never write to checked folders
+    let input_file = if tool.output_mode == OutputMode::Directory {
+        copy_to_folder(source_file, output_folder)
+    } else {
+        source_file.to_string()
+    };
+    let mut command = generate_command_from_items(&tool.path, &tool.arguments, &input_file, output_png, settings.px_size_of_generated_file);
+    let files_before = if tool.output_mode == OutputMode::Directory {
+        command.current_dir(output_folder);
+        folder_files(output_folder)
+    } else {
+        BTreeMap::new()
+    };
+    // Run command to convert svg to png
+    // Failure to start tool is reported as result of file, because it may depend on file e.g. when tool is removed during bisect
+    let mut output = match run_command(&mut command, &limits) {
+        Ok(t) => t,
+        Err(e) => return (ToolResult::failed_to_run(&tool.name, &e), command),
+    };
 
-        if settings.debug_show_always_output {
-            println!(
-                "{source_file}\nERR: {err_message:?}\nOUT: {normal_message:?}\nSTATUS: {}\n",
-                output.status
-            );
+    let output_problem = match tool.output_mode {
+        // Png is not part of messages printed by tool
+        OutputMode::Stdout => save_png_from_stdout(&mem::take(&mut output.stdout), output_png),
+        OutputMode::Directory => move_created_png(tool, &input_file, output_folder, &files_before, output_png),
+        OutputMode::File | OutputMode::Auto => None,
+    };
+
+    let all_output = output.all_output();
+    if settings.debug_show_always_output {
+        println!("{source_file}\n{all_output}\nSTATUS: {}\nTIME: {:?}\n", output.status, output.duration);
+    }
+
+    let mut outcome = Outcome::from_process(&output);
+    if outcome.is_success() {
+        if let Some(output_problem) = output_problem {
+            outcome = output_problem;
+        } else if !Path::new(output_png).is_file() {
+            outcome = Outcome::MissingOutput;
         }
-        if !output.status.success() && !settings.debug_show_always_output {
-            save_problematic_file(
-                &settings.problematic_files_path, tool_name, source_file, settings.remove_problematic_files_after_copying,
-            );
-            problematic_items.fetch_add(1, Ordering::Relaxed);
-            let mut e_msg = String::new();
-            if let Ok(message) = err_message {
-                e_msg = message.clone();
-            }
-            let mut n_msg = String::new();
-            if let Ok(message) = normal_message {
-                n_msg = message.clone();
+    }
+    let tool_result = ToolResult {
+        tool: tool.name.clone(),
+        outcome,
+        exit_code: output.status.exit_code(),
+        duration: Some(output.duration),
+        output: all_output,
+    };
+    (tool_result, command)
+}
+
+fn save_png_from_stdout(png: &[u8], output_png: &str) -> Option<Outcome> {
+    if png.is_empty() {
+        return Some(Outcome::MissingOutput);
+    }
+    if !png.starts_with(PNG_SIGNATURE) {
+        return Some(Outcome::UndecodablePng {
+            reason: "standard output of tool is not png file".to_string(),
+        });
+    }
+    if let Err(e) = fs::write(output_png, png) {
+        eprintln!("Failed to save {output_png}, reason {e}");
+    }
+    None
+}
+
+// Exactly one new or overwritten file must match `output_glob`, otherwise it is not known which file is render of tool
+fn move_created_png(
+    tool: &ToolSettings,
+    input_file: &str,
+    output_folder: &Path,
+    files_before: &BTreeMap<String, Option<SystemTime>>,
+    output_png: &str,
+) -> Option<Outcome> {
+    let file_stem = Path::new(input_file).file_stem().unwrap_or_default().to_string_lossy();
+    // Glob is validated when loading settings, and escaped name cannot break it
+    let glob = Glob::new(&tool.output_glob.replace("{NAME}", &globset::escape(&file_stem)))
+        .ok()?
+        .compile_matcher();
+    let created_files = folder_files(output_folder)
+        .into_iter()
+        .filter(|(file, modified)| files_before.get(file) != Some(modified) && glob.is_match(file))
+        .map(|(file, _)| file)
+        .collect::<Vec<_>>();
+    match created_files.as_slice() {
+        [] => Some(Outcome::MissingOutput),
+        [created_file] => {
+            let created_file = output_folder.join(created_file);
+            if created_file != Path::new(output_png) {
+                if let Err(e) = fs::rename(&created_file, output_png) {
+                    eprintln!("Failed to rename file {} to {output_png}, reason {e}", created_file.display());
+                }
             }
-            println!("\n\n{}\n{}\ncommand {:?} {:?}", e_msg, n_msg, command.get_program(), command.get_args());
-            return false;
+            None
         }
+        _ => Some(Outcome::MultipleOutputs { files: created_files }),
     }
-    true
 }
 
-fn generate_command_from_items(
-    name: &str,
-    arguments: &str,
-    source_file: &str,
-    output_file: &str,
-    px_size_of_generated_file: u32,
-    timeout: u32,
-) -> Command {
+// Paths relative to folder with modification times, to find files created or overwritten by tool
+fn folder_files(folder: &Path) -> BTreeMap<String, Option<SystemTime>> {
+    WalkDir::new(folder)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|entry| {
+            let file = entry.path().strip_prefix(folder).ok()?.to_string_lossy().to_string();
+            Some((file, entry.metadata().ok().and_then(|e| e.modified().ok())))
+        })
+        .collect()
+}
+
+fn generate_command_from_items(name: &str, arguments: &str, source_file: &str, output_file: &str, px_size_of_generated_file: u32) -> Command {
     let new_arguments = arguments.replace("{SIZE}", &px_size_of_generated_file.to_string());
-    let mut com = Command::new("timeout");
-    com.arg("-v").arg(timeout.to_string());
-    com.arg(name);
+    // Paths are absolute, because in `directory` mode tool is run in folder of output png
+    let (source_file, output_file) = (absolute_path(source_file), absolute_path(output_file));
+    let mut com = Command::new(resolve_program(name));
     // FILE must be renamed after splitting arguments by space, because source_file may contain spaces
     // and broke file
     com.args(
         new_arguments
             .split(' ')
-            .map(|e| e.replace("{FILE}", source_file).replace("{OUTPUT_FILE}", output_file)),
+            .map(|e| e.replace("{FILE}", &source_file).replace("{OUTPUT_FILE}", &output_file)),
     );
     com
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::scratch_folder;
+
+    #[test]
+    fn runs_tool_with_relative_path_in_directory_mode() {
+        // `cp` creates png with name of svg file in working directory, like tools which render to current folder
+        let cp_path = which::which("cp").unwrap();
+        let depth = std::env::current_dir().unwrap().components().count() - 1;
+        let relative_cp_path = format!("{}{}", "../".repeat(depth), cp_path.to_string_lossy().trim_start_matches('/'));
+        let tool = ToolSettings {
+            name: "cp".to_string(),
+            path: relative_cp_path,
+            png_name_ending: "_cp.png".to_string(),
+            arguments: "{FILE} relative_tool_path.png".to_string(),
+            output_mode: OutputMode::Directory,
+            output_glob: "{NAME}.png".to_string(),
+            version_arguments: String::new(),
+        };
+
+        let job = RenderJob::create();
+        let source_file = scratch_folder().join("relative_tool_path.svg").to_string_lossy().to_string();
+        fs::write(&source_file, "<svg/>").unwrap();
+        let output_png = job.output_png(&source_file, &tool);
+        let tool_result = render_with_tool(&Settings::default(), &tool, &source_file, &output_png);
+        assert_eq!(tool_result.outcome, Outcome::Success);
+        assert_eq!(fs::read(&output_png).unwrap(), b"<svg/>");
+    }
+}